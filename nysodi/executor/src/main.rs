@@ -6,6 +6,16 @@ fn main() {
     Log::set_file_name("nysodi.log");
 
     let mut executor = Executor::new();
+
+    // Replays run at the update rate they were recorded at.
+    #[cfg(feature = "nysodi")]
+    if let Ok(path) = std::env::var(nysodi::replay::REPLAY_VAR) {
+        if let Ok(recording) = nysodi::replay::Recording::load(&path) {
+            if recording.dt > 0.0 {
+                executor.set_desired_update_rate(1.0 / recording.dt);
+            }
+        }
+    }
    
     // Dynamic linking with hot reloading.
     #[cfg(feature = "dylib")]
@@ -0,0 +1,148 @@
+//! State machine driving the bots.
+//!
+//! Every tick a [`Bot`](crate::bot::Bot) gathers what it perceives into [`Senses`], and its
+//! [`StateMachine`] follows the [`Transition`]s of [`BOT_TRANSITIONS`] to pick an [`AiState`].
+//! The bot then runs the behaviour of that state only. Transitions are plain data: a new
+//! behaviour is a new state, the rows leading to and away from it, and its behaviour in the bot.
+
+use fyrox::core::{reflect::prelude::*, visitor::prelude::*};
+
+/// What a bot is doing.
+#[derive(Visit, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AiState {
+    /// Has no target and stands still.
+    #[default]
+    Idle,
+    /// Lost sight of its target and wanders around.
+    Patrol,
+    /// Moves towards its target, which is out of attack range.
+    Chase,
+    /// Hits or shoots at its target, which is in attack range.
+    Attack,
+    /// Runs away from its target.
+    Flee,
+    /// Frozen in place, by an item or a reaction.
+    Stunned,
+    /// Defeated, waiting to respawn.
+    Dead,
+}
+
+/// What a bot perceives during a tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Senses {
+    pub dead: bool,
+    pub stunned: bool,
+    pub fleeing: bool,
+    /// Whether there is a target to hunt at all.
+    pub has_target: bool,
+    /// Whether the target is within sight.
+    pub sees_target: bool,
+    /// Whether the target is within sight and attack range.
+    pub in_range: bool,
+}
+
+/// Test of the [`Senses`] that triggers a [`Transition`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Dead,
+    Alive,
+    Stunned,
+    Fleeing,
+    /// Neither stunned nor fleeing.
+    Calm,
+    NoTarget,
+    TargetLost,
+    TargetInRange,
+    TargetOutOfRange,
+}
+
+impl Condition {
+    pub fn holds(self, senses: &Senses) -> bool {
+        match self {
+            Condition::Dead => senses.dead,
+            Condition::Alive => !senses.dead,
+            Condition::Stunned => senses.stunned,
+            Condition::Fleeing => senses.fleeing,
+            Condition::Calm => !senses.stunned && !senses.fleeing,
+            Condition::NoTarget => !senses.has_target,
+            Condition::TargetLost => senses.has_target && !senses.sees_target,
+            Condition::TargetInRange => senses.sees_target && senses.in_range,
+            Condition::TargetOutOfRange => senses.sees_target && !senses.in_range,
+        }
+    }
+}
+
+/// Moves a machine in one of the `from` states to `to` when `when` holds. An empty `from` matches
+/// every state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub from: &'static [AiState],
+    pub when: Condition,
+    pub to: AiState,
+}
+
+const fn transition(from: &'static [AiState], when: Condition, to: AiState) -> Transition {
+    Transition { from, when, to }
+}
+
+const ANY: &[AiState] = &[];
+
+/// Transitions of every bot, by decreasing priority.
+pub const BOT_TRANSITIONS: &[Transition] = &[
+    transition(ANY, Condition::Dead, AiState::Dead),
+    transition(&[AiState::Dead], Condition::Alive, AiState::Idle),
+    transition(ANY, Condition::Stunned, AiState::Stunned),
+    // Fleeing needs something to flee from.
+    transition(ANY, Condition::NoTarget, AiState::Idle),
+    transition(ANY, Condition::Fleeing, AiState::Flee),
+    transition(
+        &[AiState::Stunned, AiState::Flee],
+        Condition::Calm,
+        AiState::Idle,
+    ),
+    transition(
+        &[AiState::Idle, AiState::Chase, AiState::Attack],
+        Condition::TargetLost,
+        AiState::Patrol,
+    ),
+    transition(
+        &[AiState::Idle, AiState::Patrol, AiState::Attack],
+        Condition::TargetOutOfRange,
+        AiState::Chase,
+    ),
+    transition(
+        &[AiState::Idle, AiState::Patrol, AiState::Chase],
+        Condition::TargetInRange,
+        AiState::Attack,
+    ),
+];
+
+/// Current [`AiState`] of a bot.
+#[derive(Visit, Reflect, Debug, Clone, Default)]
+pub struct StateMachine {
+    state: AiState,
+}
+
+impl StateMachine {
+    pub fn state(&self) -> AiState {
+        self.state
+    }
+
+    /// Follows the first transition out of the current state whose condition holds, until none
+    /// does, so a bot reacts within the tick it perceives a change. Returns the state the machine
+    /// left, if it changed.
+    pub fn update(&mut self, senses: &Senses, transitions: &[Transition]) -> Option<AiState> {
+        let previous = self.state;
+        // Bounded, so transitions going back and forth can't hang the game.
+        for _ in 0..transitions.len() {
+            let next = transitions.iter().find(|t| {
+                (t.from.is_empty() || t.from.contains(&self.state)) && t.when.holds(senses)
+            });
+            match next {
+                Some(t) if t.to != self.state => self.state = t.to,
+                _ => break,
+            }
+        }
+        (self.state != previous).then_some(previous)
+    }
+}
@@ -0,0 +1,96 @@
+//! Playable area of the scene.
+//!
+//! Item drops, enemy spawns, patrols and respawns all stay inside the [`Arena`] of the scene.
+//! A scene marks its arena with a node named [`ARENA_NODE`], e.g. a hidden rectangle stretched
+//! over the floor. Without one, the arena is the inside of the static colliders walling the
+//! scene. The [`Game`](crate::Game) reads the arena along with the navigation grid, once the
+//! tile sets of the scene are loaded.
+//!
+//! Nodes named [`SPAWN_POINT_NODE`] mark where the waves spawn their bots, in place of the spawn
+//! points of `waves.ron`.
+
+use crate::nav::{self, Bounds};
+use fyrox::{
+    core::algebra::{Point3, Vector2},
+    graph::SceneGraph,
+    scene::graph::Graph,
+};
+
+/// Name of the node whose rectangle is the arena. The node is a unit square scaled to the size
+/// of the arena, like a rectangle sprite.
+pub const ARENA_NODE: &str = "Arena";
+/// Name of the nodes marking the spawn points of the scene.
+pub const SPAWN_POINT_NODE: &str = "SpawnPoint";
+/// Room kept between the outer edge of the walls and the arena: one wall tile and half a unit.
+pub const WALL_MARGIN: f32 = 1.5;
+/// Walls enclosing less than this, in both directions, are obstacles rather than an arena.
+const MIN_SIZE: f32 = 4.0;
+
+/// Axis-aligned rectangle things are spawned in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arena {
+    pub min: Vector2<f32>,
+    pub max: Vector2<f32>,
+}
+
+impl Default for Arena {
+    /// Arena of scenes without walls, e.g. the headless test scenes.
+    fn default() -> Self {
+        Self::new(Vector2::new(-11.0, -4.0), Vector2::new(11.0, 17.0))
+    }
+}
+
+impl Arena {
+    pub fn new(min: Vector2<f32>, max: Vector2<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// Arena marked by the [`ARENA_NODE`] of `graph`, else the inside of its walls, else the
+    /// default arena.
+    pub fn from_scene(graph: &Graph) -> Self {
+        Self::from_node(graph)
+            .or_else(|| Self::from_walls(graph))
+            .unwrap_or_default()
+    }
+
+    fn from_node(graph: &Graph) -> Option<Self> {
+        let (_, node) = graph.find_by_name_from_root(ARENA_NODE)?;
+        let transform = node.global_transform();
+        let corners = [(-0.5, -0.5), (0.5, -0.5), (-0.5, 0.5), (0.5, 0.5)].map(|(x, y)| {
+            transform
+                .transform_point(&Point3::new(x, y, 0.0))
+                .coords
+                .xy()
+        });
+        let bounds = Bounds::of_points(corners)?;
+        Some(Self::new(bounds.min, bounds.max))
+    }
+
+    fn from_walls(graph: &Graph) -> Option<Self> {
+        let (obstacles, _) = nav::static_obstacles(graph);
+        let extent = obstacles
+            .into_iter()
+            .reduce(Bounds::union)?
+            .inflate(-WALL_MARGIN);
+        let size = extent.max - extent.min;
+        (size.x >= MIN_SIZE && size.y >= MIN_SIZE).then(|| Self::new(extent.min, extent.max))
+    }
+
+    /// The point of the arena closest to `point`.
+    pub fn clamp(&self, point: Vector2<f32>) -> Vector2<f32> {
+        point.sup(&self.min).inf(&self.max)
+    }
+
+    pub fn contains(&self, point: Vector2<f32>) -> bool {
+        self.clamp(point) == point
+    }
+}
+
+/// Positions of the [`SPAWN_POINT_NODE`]s of `graph`, in the order of the graph.
+pub fn spawn_points(graph: &Graph) -> Vec<Vector2<f32>> {
+    graph
+        .linear_iter()
+        .filter(|node| node.name() == SPAWN_POINT_NODE)
+        .map(|node| node.global_position().xy())
+        .collect()
+}
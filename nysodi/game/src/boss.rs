@@ -0,0 +1,316 @@
+//! Multi-phase boss behaviour.
+//!
+//! A boss is a [`Bot`] whose [`Archetype`](crate::enemy::Archetype) lists [`BossPhase`]s. The
+//! phase changes as the boss loses health, and every phase has its own [`Ability`] set: summoning
+//! bots, charging at the player or slamming the ground. Between two abilities the boss chases
+//! and hits the player like any bot of its archetype. The wave director spawns the boss once
+//! the waves listed before it are cleared, and defeating it wins the run.
+
+use crate::{
+    bot::Bot,
+    context::TickContext,
+    event::{BossPhaseChanged, DamageDealt},
+    health::{DamageType, Damageable},
+};
+use fyrox::{
+    core::{algebra::Vector2, pool::Handle, reflect::prelude::*, visitor::prelude::*},
+    graph::BaseSceneGraph,
+    scene::node::Node,
+};
+use serde::Deserialize;
+
+fn default_phase_health() -> f32 {
+    1.0
+}
+
+/// A stage of a boss fight.
+#[derive(Deserialize, Visit, Reflect, Debug, Clone, PartialEq, Default)]
+pub struct BossPhase {
+    /// Shown on the boss health bar.
+    pub name: String,
+    /// The phase starts once boss health drops to this fraction of its max health.
+    #[serde(default = "default_phase_health")]
+    pub health: f32,
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+}
+
+/// Special attack of a boss. The boss stands still for `windup` seconds, then uses the ability
+/// and waits `cooldown` seconds before using it again.
+#[derive(Deserialize, Visit, Reflect, Debug, Clone, PartialEq)]
+pub enum Ability {
+    /// Summons `count` bots of `archetype` around the boss, keeping at most `max_alive` of them.
+    Summon {
+        archetype: String,
+        count: u32,
+        max_alive: u32,
+        windup: f32,
+        cooldown: f32,
+    },
+    /// Dashes towards the player at `speed` for `duration` seconds, or until it runs into a
+    /// wall, hitting once for `damage` on the way.
+    Charge {
+        speed: f32,
+        duration: f32,
+        damage: f32,
+        windup: f32,
+        cooldown: f32,
+    },
+    /// Hurts the player if closer than `radius`.
+    Slam {
+        radius: f32,
+        damage: f32,
+        windup: f32,
+        cooldown: f32,
+    },
+}
+
+impl Default for Ability {
+    fn default() -> Self {
+        Ability::Slam {
+            radius: 3.0,
+            damage: 30.0,
+            windup: 1.0,
+            cooldown: 5.0,
+        }
+    }
+}
+
+impl Ability {
+    pub fn windup(&self) -> f32 {
+        match *self {
+            Ability::Summon { windup, .. }
+            | Ability::Charge { windup, .. }
+            | Ability::Slam { windup, .. } => windup,
+        }
+    }
+
+    pub fn cooldown(&self) -> f32 {
+        match *self {
+            Ability::Summon { cooldown, .. }
+            | Ability::Charge { cooldown, .. }
+            | Ability::Slam { cooldown, .. } => cooldown,
+        }
+    }
+}
+
+/// What a boss is doing.
+#[derive(Visit, Reflect, Debug, Clone, PartialEq, Default)]
+pub enum BossAction {
+    /// Chases and hits the player like any bot.
+    #[default]
+    Chasing,
+    /// Stands still before using the ability `ability` of the phase.
+    WindingUp { ability: u32, timer: f32 },
+    /// Dashes along `direction` with the charge `ability` of the phase.
+    Charging {
+        ability: u32,
+        direction: Vector2<f32>,
+        timer: f32,
+        hit: bool,
+    },
+}
+
+/// State of the phase machine of a boss.
+#[derive(Visit, Reflect, Debug, Clone, Default)]
+pub struct Boss {
+    /// Index of the current phase in the archetype.
+    phase: u32,
+    action: BossAction,
+    /// Seconds until each ability of the phase can be used again.
+    cooldowns: Vec<f32>,
+    /// Bots summoned by the boss and still in the scene.
+    summoned: Vec<Handle<Node>>,
+}
+
+impl Boss {
+    pub fn phase(&self) -> u32 {
+        self.phase
+    }
+
+    pub fn action(&self) -> &BossAction {
+        &self.action
+    }
+
+    /// Runs one tick of the boss `bot`. Returns whether an ability drives the boss; otherwise
+    /// the bot chases and attacks as usual.
+    pub fn update(&mut self, bot: &mut Bot, ctx: &mut TickContext) -> bool {
+        self.update_phase(bot, ctx);
+        let Some(phase) = bot.archetype().phases.get(self.phase as usize).cloned() else {
+            return false;
+        };
+        let dt = ctx.dt;
+        for cooldown in &mut self.cooldowns {
+            *cooldown -= dt;
+        }
+        self.summoned
+            .retain(|h| ctx.scene.graph.try_get(*h).is_some_and(|n| n.visibility()));
+
+        match self.action.clone() {
+            BossAction::Chasing => {
+                let ready = phase
+                    .abilities
+                    .iter()
+                    .enumerate()
+                    .position(|(i, ability)| self.cooldowns[i] <= 0.0 && self.can_use(ability));
+                let Some(ability) = ready else {
+                    return false;
+                };
+                self.action = BossAction::WindingUp {
+                    ability: ability as u32,
+                    timer: phase.abilities[ability].windup(),
+                };
+                bot.steer(Vector2::zeros(), 0.0, ctx);
+                true
+            }
+            BossAction::WindingUp { ability, timer } => {
+                bot.steer(Vector2::zeros(), 0.0, ctx);
+                let timer = timer - dt;
+                if timer > 0.0 {
+                    self.action = BossAction::WindingUp { ability, timer };
+                } else {
+                    self.cooldowns[ability as usize] = phase.abilities[ability as usize].cooldown();
+                    self.action = BossAction::Chasing;
+                    self.perform(ability, &phase.abilities[ability as usize], bot, ctx);
+                }
+                true
+            }
+            BossAction::Charging {
+                ability,
+                direction,
+                timer,
+                mut hit,
+            } => {
+                let Ability::Charge { speed, damage, .. } = phase.abilities[ability as usize]
+                else {
+                    self.action = BossAction::Chasing;
+                    return false;
+                };
+                bot.steer(direction, speed, ctx);
+                let distance = player_distance(bot, ctx);
+                if !hit && distance <= bot.archetype().attack_range {
+                    ctx.game.events.publish(DamageDealt {
+                        target: bot.target(),
+                        source: ctx.handle,
+                        amount: damage,
+                        kind: DamageType::Physical,
+                    });
+                    hit = true;
+                }
+                let timer = timer - dt;
+                if timer <= 0.0 || bot.has_obstacles(ctx) {
+                    bot.steer(Vector2::zeros(), 0.0, ctx);
+                    self.action = BossAction::Chasing;
+                } else {
+                    self.action = BossAction::Charging {
+                        ability,
+                        direction,
+                        timer,
+                        hit,
+                    };
+                }
+                true
+            }
+        }
+    }
+
+    /// Moves on to the last phase whose health threshold was crossed. Phases never go back,
+    /// even if the boss heals.
+    fn update_phase(&mut self, bot: &Bot, ctx: &mut TickContext) {
+        let phases = &bot.archetype().phases;
+        let health = bot.health();
+        let fraction = health.current() / health.max().max(f32::EPSILON);
+        let phase = phases
+            .iter()
+            .rposition(|p| fraction <= p.health)
+            .map_or(0, |p| p as u32)
+            .max(self.phase);
+        let abilities = phases.get(phase as usize).map_or(0, |p| p.abilities.len());
+        if phase == self.phase && self.cooldowns.len() == abilities {
+            return;
+        }
+        if phase != self.phase {
+            self.phase = phase;
+            self.action = BossAction::Chasing;
+            println!(
+                "▶ {} enters phase {}!",
+                bot.archetype().name,
+                phases[phase as usize].name
+            );
+            ctx.game.events.publish(BossPhaseChanged {
+                boss: ctx.handle,
+                phase,
+            });
+        }
+        // Abilities of a new phase are ready at once.
+        self.cooldowns = vec![0.0; abilities];
+    }
+
+    fn can_use(&self, ability: &Ability) -> bool {
+        match ability {
+            Ability::Summon { max_alive, .. } => self.summoned.len() < *max_alive as usize,
+            Ability::Charge { .. } | Ability::Slam { .. } => true,
+        }
+    }
+
+    fn perform(&mut self, index: u32, ability: &Ability, bot: &Bot, ctx: &mut TickContext) {
+        let position = ctx.scene.graph[ctx.handle].global_position().xy();
+        match ability {
+            Ability::Summon {
+                archetype,
+                count,
+                max_alive,
+                ..
+            } => {
+                let count = (*count).min(max_alive.saturating_sub(self.summoned.len() as u32));
+                let game = &mut *ctx.game;
+                let summoned = game.waves.summon(
+                    archetype,
+                    count,
+                    position,
+                    ctx.scene,
+                    &mut game.rng,
+                    &mut game.events,
+                );
+                println!(
+                    "▶ {} summons {} {}!",
+                    bot.archetype().name,
+                    summoned.len(),
+                    archetype
+                );
+                self.summoned.extend(summoned);
+            }
+            Ability::Charge { duration, .. } => {
+                let target = ctx.scene.graph[bot.target()].global_position().xy();
+                let direction = (target - position)
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_default();
+                self.action = BossAction::Charging {
+                    ability: index,
+                    direction,
+                    timer: *duration,
+                    hit: false,
+                };
+            }
+            Ability::Slam { radius, damage, .. } => {
+                println!("▶ {} slams the ground!", bot.archetype().name);
+                if player_distance(bot, ctx) <= *radius {
+                    ctx.game.events.publish(DamageDealt {
+                        target: bot.target(),
+                        source: ctx.handle,
+                        amount: *damage,
+                        kind: DamageType::Physical,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn player_distance(bot: &Bot, ctx: &TickContext) -> f32 {
+    let graph = &ctx.scene.graph;
+    let Some(player) = graph.try_get(bot.target()) else {
+        return f32::INFINITY;
+    };
+    (player.global_position().xy() - graph[ctx.handle].global_position().xy()).norm()
+}
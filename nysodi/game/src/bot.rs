@@ -1,6 +1,12 @@
 // ANCHOR: imports
-use crate::Game;
-use crate::Player;
+use crate::ai::{AiState, Senses, StateMachine, BOT_TRANSITIONS};
+use crate::boss::Boss;
+use crate::context::TickContext;
+use crate::enemy::{Archetype, Attack};
+use crate::event::{DamageDealt, EntityDied, EntityKind};
+use crate::health::{DamageType, Damageable, Health};
+use crate::projectile::{self, Faction};
+use crate::random_point_around;
 use fyrox::{
     core::{
         algebra::{Vector2, Vector3},
@@ -23,13 +29,29 @@ use fyrox::{
         rigidbody::RigidBodyType,
     },
     script::{ScriptContext, ScriptTrait},
-    event::{ElementState, Event, WindowEvent},
-    keyboard::{KeyCode, PhysicalKey},
     gui::texture::Texture,
-    rand::{self, Rng},
+    rand::Rng,
 };
 // ANCHOR_END: imports
 
+/// Chasing bots stop this much closer to the player than their attack range.
+const CHASE_MARGIN: f32 = 0.4;
+/// Patrolling bots walk to random points up to this far away.
+const PATROL_RADIUS: f32 = 4.0;
+/// Distance at which a patrolling bot has reached its point.
+const PATROL_TOLERANCE: f32 = 0.3;
+/// Seconds between two path searches of a bot walking around an obstacle.
+const REPATH_INTERVAL: f32 = 0.5;
+/// Distance at which a bot has reached a point of its path.
+const WAYPOINT_TOLERANCE: f32 = 0.2;
+/// Bots in range step away from bots pushing harder than this, see
+/// [`Flock::separation`](crate::flock::Flock::separation).
+const CROWDED: f32 = 0.3;
+/// Speed of a bot running away from its target.
+const FLEE_SPEED: f32 = 2.0;
+/// Bots closer than this to their target play the attack animation.
+const ATTACK_ANIMATION_RANGE: f32 = 1.4;
+
 #[derive(Visit, Reflect, Debug, Clone, TypeUuidProvider, ComponentProvider)]
 #[type_uuid(id = "d2786d36-a0af-4e67-916a-438af62f818b")]
 #[visit(optional)]
@@ -38,6 +60,9 @@ pub struct Bot {
     rectangle: InheritableVariable<Handle<Node>>,
     // ANCHOR_END: visual_fields
 
+    /// Stats, attack and look of the kind of enemy this bot is.
+    archetype: Archetype,
+
     // ANCHOR: movement_fields
     speed: InheritableVariable<f32>,
     direction: Vector2<f32>,
@@ -46,6 +71,8 @@ pub struct Bot {
     // ANCHOR_END: movement_fields
 
     // ANCHOR: target_fields
+    /// The player, looked up from [`Game::player`](crate::Game::player) every tick, so it is
+    /// not saved.
     #[visit(skip)]
     #[reflect(hidden)]
     target: Handle<Node>,
@@ -54,12 +81,11 @@ pub struct Bot {
     // ANCHOR: animation_fields
     animations: Vec<SpriteSheetAnimation>,
     current_animation: InheritableVariable<u32>,
-    health: f32,
-    max_health: f32,
+    health: Health,
     health_fill_handle: Handle<Node>,
     damage_timer: f32,
+    respawn: bool,
     respawn_timer: Option<f32>,
-    pending_health_update: Option<f32>,
 
     pub reaction_timer: f32,
     pub reaction_state: ReactionState,
@@ -69,6 +95,28 @@ pub struct Bot {
 
     target_handle: Option<Handle<Node>>,
     target_sprite_timer: f32,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    hit_marker_pending: bool,
+
+    frozen_timer: f32,
+
+    /// Phase state machine, only used by bosses.
+    boss: Boss,
+
+    /// Picks what the bot does every tick.
+    ai: StateMachine,
+    /// Point a patrolling bot walks to.
+    patrol_point: Option<Vector2<f32>>,
+
+    /// Points to walk through to reach the player around obstacles, searched again every
+    /// `REPATH_INTERVAL` seconds.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    path: Vec<Vector2<f32>>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    repath_timer: f32,
 }
 
 #[derive(Visit, Reflect, Debug, Clone, Copy)]
@@ -81,6 +129,7 @@ pub enum ReactionState {
 impl Default for Bot {
     fn default() -> Self {
         Self {
+            archetype: Archetype::default(),
             speed: 1.0.into(),
             direction: Vector2::new(0.0, 0.0),
             front_obstacle_sensor: Default::default(),
@@ -89,18 +138,24 @@ impl Default for Bot {
             rectangle: Default::default(),
             animations: Default::default(),
             current_animation: Default::default(),
-            health: 100.0,
-            max_health: 100.0,
+            health: Health::default(),
             health_fill_handle: Handle::NONE,
             damage_timer: 0.0,
+            respawn: true,
             respawn_timer: None,
-            pending_health_update: None,
             reaction_state: ReactionState::Motionless,
             reaction_timer: 0.0,
             reaction_cooldown: 0.0,
             //has_reacted: false,
             target_handle: None,
             target_sprite_timer: 0.0,
+            hit_marker_pending: false,
+            frozen_timer: 0.0,
+            boss: Boss::default(),
+            ai: StateMachine::default(),
+            patrol_point: None,
+            path: Vec::new(),
+            repath_timer: 0.0,
         }
     }
 }
@@ -109,37 +164,53 @@ impl Default for Bot {
 // ANCHOR: has_ground_in_front
 impl Bot {
 
-    pub fn get_health(&self) -> f32 {
-        self.health
+    pub fn archetype(&self) -> &Archetype {
+        &self.archetype
     }
 
-    pub fn set_health(&mut self, new_health: f32) {
-        self.pending_health_update = Some(new_health);
+    /// Turns the bot into an enemy of the given archetype, with full health.
+    pub fn set_archetype(&mut self, archetype: Archetype) {
+        self.health.set_max(archetype.health);
+        self.archetype = archetype;
     }
 
-    pub fn set_health_fill_handle(&mut self, handle: Handle<Node>) {
-        self.health_fill_handle = handle;
+    /// Whether the archetype of the bot has boss phases.
+    pub fn is_boss(&self) -> bool {
+        !self.archetype.phases.is_empty()
     }
 
-    fn update_health_bar(&mut self, context: &mut ScriptContext) {
-        if self.health_fill_handle.is_some() {
-            let health_ratio = self.health / self.max_health;
-            let full_width = 100.0; // Set to your bar's full width
+    /// Phase state machine of a boss, `None` for regular bots.
+    pub fn boss(&self) -> Option<&Boss> {
+        self.is_boss().then_some(&self.boss)
+    }
 
-            if let Some(health_fill_rect) = context.scene.graph.try_get_mut(self.health_fill_handle).and_then(|n| n.cast_mut::<Rectangle>()) {
-                let mut local_transform = health_fill_rect.local_transform_mut();
-                local_transform.set_scale(Vector3::new(health_ratio, local_transform.scale().y, local_transform.scale().z));
-            }
+    /// Bots that don't respawn remove themselves from the scene once defeated.
+    pub fn set_respawn(&mut self, respawn: bool) {
+        self.respawn = respawn;
+    }
 
-            if let Some(health_fill_rect) = context.scene.graph.try_get_mut(self.health_fill_handle).and_then(|n| n.cast_mut::<Rectangle>()) {
-                let mut local_transform = health_fill_rect.local_transform_mut();
-                local_transform.set_position(Vector3::new((full_width - health_ratio * full_width) / 200.0, local_transform.position().y, local_transform.position().z));
-            }
-        }
+    /// Node the bot chases.
+    pub fn target(&self) -> Handle<Node> {
+        self.target
+    }
+
+    /// Seconds until a defeated bot comes back, `None` while it is alive.
+    pub fn respawn_timer(&self) -> Option<f32> {
+        self.respawn_timer
     }
 
-    pub fn trigger_reaction(&mut self) {
-        if fyrox::rand::thread_rng().gen_bool(0.5) { // This line generates a random boolean value that is true with a 50% probability and false with a 50% probability
+    pub fn set_health_fill_handle(&mut self, handle: Handle<Node>) {
+        self.health_fill_handle = handle;
+    }
+
+    fn update_health_bar(&self, ctx: &mut TickContext) {
+        self.health
+            .update_bar(&mut ctx.scene.graph, self.health_fill_handle);
+    }
+
+    pub fn trigger_reaction(&mut self, rng: &mut impl Rng) {
+        if rng.gen_bool(0.5) {
+            // This line generates a random boolean value that is true with a 50% probability and false with a 50% probability
             self.reaction_state = ReactionState::Motionless;
         } else {
             self.reaction_state = ReactionState::RunningAway;
@@ -147,28 +218,60 @@ impl Bot {
         self.reaction_timer = 3.0;
     }
 
+    /// Stops the bot in place for `duration` seconds.
+    pub fn freeze(&mut self, duration: f32) {
+        self.frozen_timer = self.frozen_timer.max(duration);
+    }
+
     pub fn set_animations(&mut self, animations: Vec<SpriteSheetAnimation>) {
         self.animations = animations;
     }
 
-    fn locate_target(&mut self, ctx: &mut ScriptContext) {
-        let game = ctx.plugins.get::<Game>();
-        self.target = game.player;
+    fn locate_target(&mut self, ctx: &mut TickContext) {
+        self.target = ctx.game.player;
     }
 
-    fn move_to_target(&mut self, ctx: &mut ScriptContext) {
+    fn move_to_target(&mut self, ctx: &mut TickContext) {
         // Move only if the bot is visible
         if ctx.scene.graph[ctx.handle].visibility() {
             // Calculate the target position and the bot's position
-            let tp = ctx.scene.graph[self.target].global_position().xy();
+            let Some(target) = ctx.scene.graph.try_get(self.target) else {
+                return;
+            };
+            let tp = target.global_position().xy();
             let sp = ctx.scene.graph[ctx.handle].global_position().xy();
             let delta = tp - sp;
             let dist = (delta.x.powi(2) + delta.y.powi(2)).sqrt();
 
+            // Ranged bots back away from a player coming too close
+            let keep_distance = match self.archetype.attack {
+                Attack::Ranged { keep_distance, .. } => keep_distance,
+                _ => 0.0,
+            };
+
+            // Bots spread around the player instead of all heading for the same point
+            let flock = &ctx.game.flock;
+            let stop = self.archetype.attack_range - CHASE_MARGIN;
+            let slot = flock.slot(ctx.handle, stop).unwrap_or(tp);
+            let separation = flock.separation(ctx.handle, sp);
+
             // Adjust direction and speed based on distance
-            if dist > 1.1 {
-                self.direction = delta / dist;
-                self.speed.set_value_and_mark_modified(1.2);
+            if dist > stop {
+                let toward = (slot - sp)
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or(delta / dist);
+                let spread = (toward + separation)
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or(toward);
+                self.direction = self.route(sp, tp, ctx).unwrap_or(spread);
+                self.speed.set_value_and_mark_modified(self.archetype.speed);
+            } else if dist < keep_distance && dist > f32::EPSILON {
+                self.direction = -delta / dist;
+                self.speed.set_value_and_mark_modified(self.archetype.speed);
+            } else if separation.norm() > CROWDED {
+                self.direction = separation.normalize();
+                self.speed
+                    .set_value_and_mark_modified(self.archetype.speed / 2.0);
             } else {
                 self.direction = Vector2::new(0.0, 0.0);
                 self.speed.set_value_and_mark_modified(0.0);
@@ -177,8 +280,46 @@ impl Bot {
     }
     // ANCHOR_END: search_target
 
-    /// Apply velocity to the bot's RigidBody2D and flip sprite to always face player
-    fn do_move(&mut self, ctx: &mut ScriptContext) {
+    /// Direction to walk from `from` to reach `to` around the obstacles between them, `None`
+    /// when nothing is in the way.
+    fn route(
+        &mut self,
+        from: Vector2<f32>,
+        to: Vector2<f32>,
+        ctx: &mut TickContext,
+    ) -> Option<Vector2<f32>> {
+        let nav = &mut ctx.game.nav;
+        if nav.is_clear(from, to) {
+            self.path.clear();
+            return None;
+        }
+        self.repath_timer -= ctx.dt;
+        if (self.path.is_empty() || self.repath_timer <= 0.0) && nav.try_search() {
+            self.path = nav.find_path(from, to).unwrap_or_default();
+            self.repath_timer = REPATH_INTERVAL;
+        }
+        // Drop the points reached, and the ones the bot can skip
+        while self
+            .path
+            .first()
+            .is_some_and(|p| (p - from).norm() < WAYPOINT_TOLERANCE)
+            || (self.path.len() > 1 && nav.is_clear(from, self.path[1]))
+        {
+            self.path.remove(0);
+        }
+        let next = self.path.first()?;
+        (next - from).try_normalize(f32::EPSILON)
+    }
+
+    /// Moves the bot along `direction` at `speed`, facing the player.
+    pub(crate) fn steer(&mut self, direction: Vector2<f32>, speed: f32, ctx: &mut TickContext) {
+        self.direction = direction;
+        self.speed.set_value_and_mark_modified(speed);
+        self.do_move(ctx);
+    }
+
+    /// Apply velocity to the bot's RigidBody2D and flip sprite to face the player
+    fn do_move(&mut self, ctx: &mut TickContext) {
         // Move only if the bot is visible
         if ctx.scene.graph[ctx.handle].visibility() {
             // Set movement velocity
@@ -189,17 +330,29 @@ impl Bot {
                 );
                 rb.set_lin_vel(vel);
             }
-            // Compute direction to face player
-            let tp_x = ctx.scene.graph[self.target].global_position().x;
+            // Compute direction to face player, or the way the bot walks when not hunting
             let sp_x = ctx.scene.graph[ctx.handle].global_position().x;
-            let flip = (tp_x - sp_x).signum();
+            let tp_x = match self.ai.state() {
+                AiState::Idle | AiState::Patrol => None,
+                _ => ctx
+                    .scene
+                    .graph
+                    .try_get(self.target)
+                    .map(|t| t.global_position().x),
+            };
+            let flip = match tp_x {
+                Some(tp_x) => (tp_x - sp_x).signum(),
+                None if self.direction.x != 0.0 => self.direction.x.signum(),
+                None => 1.0,
+            };
             // Invert flip if sprite's default orientation is opposite
             let scale_x = -flip;
             // Apply sprite flip
+            let scale = self.archetype.sprite.scale;
             if let Some(rect_node) = ctx.scene.graph.try_get_mut(*self.rectangle) {
                 rect_node.local_transform_mut().set_scale(Vector3::new(
-                    2.0 * scale_x,
-                    2.0,
+                    scale * scale_x,
+                    scale,
                     1.0,
                 )); 
             }
@@ -213,7 +366,7 @@ impl Bot {
     // ANCHOR_END: do_move
 
     // ANCHOR: has_obstacles
-    fn has_obstacles(&mut self, ctx: &mut ScriptContext) -> bool {
+    pub(crate) fn has_obstacles(&mut self, ctx: &mut TickContext) -> bool {
         let graph = &ctx.scene.graph;
 
         // Select the sensor using current walking direction.
@@ -253,7 +406,7 @@ impl Bot {
     }
     // ANCHOR_END: has_obstacles
 
-    fn spawn_target_sprite(&mut self, ctx: &mut ScriptContext) -> Handle<Node> {
+    fn spawn_target_sprite(&mut self, ctx: &mut TickContext) -> Handle<Node> {
         self.target_sprite_timer = f32::EPSILON; // Start the timer
 
         // Check if the target sprite already exists
@@ -297,129 +450,97 @@ impl Bot {
     }
 }
 
-impl ScriptTrait for Bot {
-    fn on_start(&mut self, ctx: &mut ScriptContext) {
+impl Bot {
+    pub fn start(&mut self, ctx: &mut TickContext) {
         // Locate the player as the target
         self.locate_target(ctx);
 
         // Initialize health bar or other visual elements if needed
         self.update_health_bar(ctx);
+        self.apply_sprite(ctx);
 
         if let Some(bot_node) = ctx.scene.graph.try_get_mut(ctx.handle) {
             println!("▶ {} initialized with target: {:?}", bot_node.name(), self.target);
         }
     }
     
-    fn on_update(&mut self, ctx: &mut ScriptContext) {
-        // 0) Always update target first
+    pub fn update(&mut self, ctx: &mut TickContext) {
+        // Always update target first
         self.locate_target(ctx);
 
-        // 1) Pending health update & respawn
-        if let Some(new_health) = self.pending_health_update.take() {
-            self.health = new_health;
-            self.update_health_bar(ctx);
-
-            if self.health <= 0.0 {
-                self.current_animation.set_value_and_mark_modified(1); // Play death animation
-                // Respawn timer
-                if self.respawn_timer.is_none() {
-                    // Award points and hide the bot only once
-                    ctx.plugins.get_mut::<Game>().bot_kill_count += 1;
-                    ctx.plugins.get_mut::<Game>().total_score += 10.0;
-                    if let Some(bot_node) = ctx.scene.graph.try_get_mut(ctx.handle) {
-                        println!(
-                            "▶ {} defeated! +10 points — total_score = {}",
-                            bot_node.name(),
-                            ctx.plugins.get::<Game>().total_score
-                        );
-                    }
-            
-                    if let Some(n) = ctx.scene.graph.try_get_mut(ctx.handle) {
-                        n.set_visibility(false);
-                    }
-            
-                    // Initialize the respawn timer
-                    self.respawn_timer = Some(0.0);
-                } else {
-                    // Increment the respawn timer
-                    if let Some(t) = &mut self.respawn_timer {
-                        *t += ctx.dt;
-                        if *t >= 3.0 {
-                            self.health = self.max_health;
-                            self.respawn_timer = None;
-                            //self.has_reacted = false; // Reset reaction state
-                            if let Some(n) = ctx.scene.graph.try_get_mut(ctx.handle) {
-                                n.set_visibility(true);
-                            }
-                            if let Some(bot_node) = ctx.scene.graph.try_get_mut(ctx.handle) {
-                                println!("▶ {} respawned!", bot_node.name());
-                            }
-
-                            self.update_health_bar(ctx);
-                        }
-                    }
-                }
-                return;
-            }
+        // Hits, death & respawn
+        self.update_health(ctx);
+        if !ctx.scene.graph.is_valid_handle(ctx.handle) {
+            // Defeated and removed from the scene.
+            return;
+        }
+        if std::mem::take(&mut self.hit_marker_pending) {
+            self.show_hit_marker(ctx);
+        }
+        if !self.health.is_dead() && self.frozen_timer <= 0.0 {
+            self.update_reaction(ctx);
         }
 
-        if self.health <= 0.0 {
-            if let Some(prev_target) = self.target_handle.take() {
-                if let Some(prev_node) = ctx.scene.graph.try_get_mut(prev_target) {
-                    ctx.scene.graph.remove_node(prev_target);
-                    println!("▶ Previous target sprite removed.");
-                }
+        let senses = self.sense(ctx);
+        self.ai.update(&senses, BOT_TRANSITIONS);
+        match self.ai.state() {
+            AiState::Dead => {
+                self.wait_for_respawn(ctx);
+                return;
             }
-            // Respawn timer
-            if let Some(t) = &mut self.respawn_timer {
-                *t += ctx.dt;
-                if *t >= 3.0 {
-                    self.health = self.max_health;
-                    self.respawn_timer = None;
-                    //self.has_reacted = false; // Reset reaction state
-                    // Get the player's position first (immutable borrow)
-                    let player_pos = ctx.scene.graph[self.target].global_position().xy();
-
-                    if let Some(n) = ctx.scene.graph.try_get_mut(ctx.handle) {
-                        let mut rng = rand::thread_rng();
-                        let offset_x: f32 = rng.gen_range(-5.0..=5.0);
-                        let offset_y: f32 = rng.gen_range(-5.0..=5.0);
-
-                        let mut position = Vector2::new(player_pos.x + offset_x, player_pos.y + offset_y);
-                        position.x = position.x.clamp(-11.0, 11.0);
-                        position.y = position.y.clamp(-4.0, 17.0);
-
-                        // Set new random position near the player
-                        n.local_transform_mut().set_position(Vector3::new(position.x, position.y, 0.0));
-                        n.set_visibility(true);
-
-                        println!(
-                            "▶ {:?} respawned at ({:.2}, {:.2})",
-                            n.name(),
-                            position.x,
-                            position.y
-                        );
-                        //n.local_transform_mut().set_position(Vector3::new(0.0, 0.0, 0.0));
-                        n.set_visibility(true);
-                    }
-                    
-                    self.update_health_bar(ctx);
-                }
-            } else {
-                self.respawn_timer = Some(0.0);
+            AiState::Stunned => self.stand_stunned(ctx),
+            AiState::Flee => self.flee(ctx),
+            AiState::Idle => {
+                self.steer(Vector2::zeros(), 0.0, ctx);
+                self.animate(3, ctx);
             }
-            return;
+            AiState::Patrol => self.patrol(ctx),
+            AiState::Chase | AiState::Attack => self.hunt(ctx),
+        }
+        if ctx.scene.graph.is_valid_handle(ctx.handle) {
+            self.update_target_item(ctx);
+        }
+    }
+
+    /// Current state of the AI of the bot.
+    pub fn state(&self) -> AiState {
+        self.ai.state()
+    }
+
+    /// What the bot perceives of itself and of its target this tick.
+    fn sense(&self, ctx: &TickContext) -> Senses {
+        let graph = &ctx.scene.graph;
+        let reacting = self.reaction_timer > 0.0;
+        let distance = graph.try_get(self.target).map(|target| {
+            (target.global_position().xy() - graph[ctx.handle].global_position().xy()).norm()
+        });
+        let sees_target =
+            distance.is_some_and(|d| self.archetype.sight.is_none_or(|sight| d <= sight));
+        Senses {
+            dead: self.health.is_dead(),
+            stunned: self.frozen_timer > 0.0
+                || (reacting && matches!(self.reaction_state, ReactionState::Motionless)),
+            fleeing: reacting && matches!(self.reaction_state, ReactionState::RunningAway),
+            has_target: distance.is_some(),
+            sees_target,
+            in_range: sees_target && distance.is_some_and(|d| d <= self.archetype.attack_range),
         }
+    }
 
-        // 2) Trigger reaction
-        let bot_kill_count = ctx.plugins.get::<Game>().bot_kill_count;
+    /// Triggers a reaction once enough bots were defeated, and counts down the current one.
+    fn update_reaction(&mut self, ctx: &mut TickContext) {
+        let bot_kill_count = ctx.game.bot_kill_count;
         // Increment the cooldown timer
         self.reaction_cooldown += ctx.dt;
         // If 10 seconds have passed, trigger a reaction and reset the timer
-        if bot_kill_count >= 6 && self.reaction_cooldown >= 10.0 && self.reaction_timer <= 0.0 {
-            self.trigger_reaction();
+        if bot_kill_count >= 6
+            && self.reaction_cooldown >= 10.0
+            && self.reaction_timer <= 0.0
+            && !self.is_boss()
+        {
+            self.trigger_reaction(&mut ctx.game.rng);
             self.reaction_cooldown = 0.0; // Reset the cooldown timer
-            ctx.plugins.get_mut::<Game>().bot_kill_count = 0; // Reset the kill count
+            ctx.game.bot_kill_count = 0; // Reset the kill count
             if let Some(bot_node) = ctx.scene.graph.try_get_mut(ctx.handle) {
                 println!(
                     "▶ Reaction triggered for {}: {:?} for 3s",
@@ -428,107 +549,179 @@ impl ScriptTrait for Bot {
                 );
             }
         }
-
-        // 3) Handle freeze/flee
         if self.reaction_timer > 0.0 {
             self.reaction_timer -= ctx.dt;
-            if self.reaction_timer > 0.0 {
-                let me = ctx.scene.graph[ctx.handle].global_position().xy();
-                let them = ctx.scene.graph[self.target].global_position().xy();
-                match self.reaction_state {
-                    ReactionState::Motionless => {
-                        self.direction = Vector2::zeros();
-                        self.speed.set_value_and_mark_modified(0.0);
-                         self.current_animation.set_value_and_mark_modified(4);
-                    }
-                    ReactionState::RunningAway => {
-                        self.direction = (me - them).normalize();
-                        self.speed.set_value_and_mark_modified(2.0);
-                        self.current_animation.set_value_and_mark_modified(2);
-                    }
-                }
-                self.do_move(ctx);
+        }
+    }
+
+    /// Removes the hit marker of a defeated bot and brings it back after a while.
+    fn wait_for_respawn(&mut self, ctx: &mut TickContext) {
+        if let Some(prev_target) = self.target_handle.take() {
+            if ctx.scene.graph.is_valid_handle(prev_target) {
+                ctx.scene.graph.remove_node(prev_target);
+                println!("▶ Previous target sprite removed.");
+            }
+        }
+        // Respawn timer
+        if let Some(t) = &mut self.respawn_timer {
+            *t += ctx.dt;
+            if *t >= 3.0 {
+                self.revive(ctx);
+            }
+        } else {
+            self.respawn_timer = Some(0.0);
+        }
+    }
+
+    /// Frozen bots neither move nor attack.
+    fn stand_stunned(&mut self, ctx: &mut TickContext) {
+        if self.frozen_timer > 0.0 {
+            self.frozen_timer -= ctx.dt;
+            self.direction = Vector2::zeros();
+        } else {
+            // Motionless reaction
+            self.direction = Vector2::zeros();
+            self.speed.set_value_and_mark_modified(0.0);
+            self.current_animation.set_value_and_mark_modified(4);
+        }
+        self.do_move(ctx);
+    }
+
+    fn flee(&mut self, ctx: &mut TickContext) {
+        let me = ctx.scene.graph[ctx.handle].global_position().xy();
+        let Some(them) = ctx.scene.graph.try_get(self.target) else {
+            return;
+        };
+        let away = (me - them.global_position().xy())
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_default();
+        self.current_animation.set_value_and_mark_modified(2);
+        self.steer(away, FLEE_SPEED, ctx);
+    }
+
+    /// Walks at half speed to random walkable points around the bot until the target comes into
+    /// sight.
+    fn patrol(&mut self, ctx: &mut TickContext) {
+        let position = ctx.scene.graph[ctx.handle].global_position().xy();
+        let reached = self.patrol_point.is_none_or(|point| {
+            (point - position).norm() < PATROL_TOLERANCE || ctx.game.nav.is_blocked(point)
+        });
+        if reached || self.has_obstacles(ctx) {
+            let point = random_point_around(
+                position,
+                1.0,
+                PATROL_RADIUS,
+                &ctx.game.arena,
+                &mut ctx.game.rng,
+            );
+            self.patrol_point = Some(point);
+        }
+        let direction = self
+            .patrol_point
+            .and_then(|point| (point - position).try_normalize(f32::EPSILON))
+            .unwrap_or_default();
+        self.steer(direction, self.archetype.speed / 2.0, ctx);
+        self.update_health_bar(ctx);
+        self.animate(2, ctx);
+    }
+
+    /// Chases the target and attacks it once in range.
+    fn hunt(&mut self, ctx: &mut TickContext) {
+        self.patrol_point = None;
+        let Some(target) = ctx.scene.graph.try_get(self.target) else {
+            return;
+        };
+        let target_position = target.global_position();
+
+        // Bosses use the abilities of their phase, and chase in between
+        if self.is_boss() {
+            let mut boss = std::mem::take(&mut self.boss);
+            let acting = boss.update(self, ctx);
+            self.boss = boss;
+            if acting {
+                self.update_health_bar(ctx);
                 return;
             }
         }
 
-        // 4) Normal chase & move
         self.move_to_target(ctx);
         self.do_move(ctx);
-
-        // 5) Damage on contact
         self.update_health_bar(ctx);
-        let player_pos = ctx.scene.graph[self.target].global_position().xy();
-        let bot_pos = ctx.scene.graph[ctx.handle].global_position().xy();
-        let dist = (player_pos - bot_pos).norm();
-        // Check if the bot's visibility is set to true before allowing damage
-        if dist <= 1.5 {
-            if let Some(bot_node) = ctx.scene.graph.try_get_mut(ctx.handle) {
-                // Ensure that bot visibility is true (Option<bool> to bool comparison)
-                if bot_node.visibility() == true {
-                    self.damage_timer += ctx.dt;
-                    if self.damage_timer >= 0.75 {
-                        if let Some(pn) = ctx.scene.graph.try_get_mut(ctx.plugins.get::<Game>().player) {
-                            if let Some(ps) = pn.script_mut(0).and_then(|s| s.cast_mut::<Player>()) {
-                                if !ps.game_over {
-                                    ps.health = (ps.health - 20.0).max(0.0);
-                                    println!("▶ Player hit! Health = {}", ps.health);
-                                    if ps.health <= 0.0 {
-                                        ps.game_over = true;
-                                        println!("▶ Player defeated!");
-                                    }
-                                }
-                            }
-                        }
-                        self.damage_timer = 0.0;
-                    }
-                } else {
-                    println!("▶ Bot is not visible, no damage dealt to the Player.");
-                }
+
+        if self.ai.state() == AiState::Attack {
+            if !self.attack(ctx) {
+                // The bot blew itself up.
+                return;
             }
         } else {
             self.damage_timer = 0.0;
         }
 
-        // 5) Determine the animation index based on the bot's state
-        let mut new_index = *self.current_animation;
-
-        // If bot has a target and is close enough, attack
-        if self.target.is_some() {
-            let target = self.target;
-            let target_position = ctx.scene.graph[target].global_position();
-            let self_position = ctx.scene.graph[ctx.handle].global_position();
-            let distance = target_position.metric_distance(&self_position);
-
-            if distance < 1.4 {
-                new_index = 0; // Attack
-            } else if self.direction.x.abs() > 0.0 || self.direction.y.abs() > 0.0 {
-                new_index = 2; // Walk
-            }
+        // If bot is close enough, play the attack animation
+        let distance = (target_position - ctx.scene.graph[ctx.handle].global_position()).norm();
+        let index = if distance < ATTACK_ANIMATION_RANGE {
+            0 // Attack
         } else if self.direction.x.abs() > 0.0 || self.direction.y.abs() > 0.0 {
-            new_index = 2; // Walk
+            2 // Walk
         } else {
-            new_index = 3; // Idle
+            *self.current_animation
+        };
+        self.animate(index, ctx);
+    }
+
+    /// Hits, shoots at or explodes next to the target in range. Returns whether the bot
+    /// survived its attack.
+    fn attack(&mut self, ctx: &mut TickContext) -> bool {
+        let Some(player) = ctx.scene.graph.try_get(self.target) else {
+            return true;
+        };
+        let player_pos = player.global_position().xy();
+        let bot_pos = ctx.scene.graph[ctx.handle].global_position().xy();
+        // Check if the bot's visibility is set to true before allowing damage
+        if !ctx.scene.graph[ctx.handle].visibility() {
+            println!("▶ Bot is not visible, no damage dealt to the Player.");
+            return true;
         }
+        if let Attack::Explode { radius } = self.archetype.attack {
+            self.explode(radius, (player_pos - bot_pos).norm(), ctx);
+            return false;
+        }
+        self.damage_timer += ctx.dt;
+        if self.damage_timer >= self.archetype.cooldown {
+            if let Attack::Ranged { projectile, .. } = &self.archetype.attack {
+                projectile::fire(
+                    projectile,
+                    self.archetype.damage,
+                    Faction::Enemy,
+                    ctx.handle,
+                    bot_pos,
+                    player_pos - bot_pos,
+                    ctx,
+                );
+            } else {
+                ctx.game.events.publish(DamageDealt {
+                    target: self.target,
+                    source: ctx.handle,
+                    amount: self.archetype.damage,
+                    kind: DamageType::Physical,
+                });
+            }
+            self.damage_timer = 0.0;
+        }
+        true
+    }
 
+    /// Switches to the animation `index`, if it changed, and plays it.
+    fn animate(&mut self, index: u32, ctx: &mut TickContext) {
         // Only switch animations when the index changes
-        if *self.current_animation != new_index {
-            self.current_animation.set_value_and_mark_modified(new_index);
+        if *self.current_animation != index {
+            self.current_animation.set_value_and_mark_modified(index);
         }
-
-        // 6) Animation update
         if let Some(anim) = self.animations.get_mut(*self.current_animation as usize) {
             anim.update(ctx.dt);
             if !anim.is_playing() {
                 anim.play();
             }
-            // println!(
-            //     "Playing animation index: {}, current frame: {}, looping: {}, playing: {}",
-            //     *self.current_animation,
-            //     anim.current_frame(),
-            //     anim.is_looping(),
-            //     anim.is_playing()
-            // );
             if let Some(rect) = ctx.scene.graph.try_get_mut(*self.rectangle)
                 .and_then(|n| n.cast_mut::<Rectangle>())
             {
@@ -536,8 +729,10 @@ impl ScriptTrait for Bot {
                 rect.set_uv_rect(anim.current_frame_uv_rect().unwrap_or_default());
             }
         }
+    }
 
-        // 7) Check for target item for the current self bot instance
+    /// Keeps the hit marker of the bot on it, and hides it shortly after the hit.
+    fn update_target_item(&mut self, ctx: &mut TickContext) {
         let bot_position = ctx.scene.graph[ctx.handle].global_position();
 
         let target_item_handle = ctx
@@ -572,100 +767,179 @@ impl ScriptTrait for Bot {
                 self.target_sprite_timer = 0.0;
             }
         }
-        
-        // 8) Check for target item nodes in the scene graph
-        // This is a debug print to check how many target item nodes are in the scene graph
-        let target_count = ctx.scene.graph.pair_iter_mut()
-            .filter(|(_, node)| node.name() == "TargetItem")
-            .count();
-
-        //println!("▶ Number of target item nodes in scene graph: {}", target_count);
-
-    }
-
-
-    fn on_os_event(&mut self, event: &Event<()>, ctx: &mut ScriptContext) {
-        if let Event::WindowEvent { event, .. } = event {
-            if let WindowEvent::KeyboardInput { event, .. } = event {
-                if let PhysicalKey::Code(keycode) = event.physical_key {
-                    let pressed = event.state == ElementState::Pressed;
-                    let released = event.state == ElementState::Released;
-
-                    match event.physical_key {
-                        PhysicalKey::Code(KeyCode::ShiftLeft) | PhysicalKey::Code(KeyCode::ShiftRight) if pressed => {
-                            // Check if the player is within a 2-tile radius
-                            let player_position = ctx.scene.graph[self.target].global_position().xy();
-                            let bot_position = ctx.scene.graph[ctx.handle].global_position().xy();
-                            let distance = (player_position - bot_position).norm();
-
-                            if distance <= 2.0 {
-                                // Check if the player is not in a game over state
-                                let mut game = ctx.plugins.get_mut::<Game>();
-                                if let Some(pn) = ctx.scene.graph.try_get_mut(ctx.plugins.get::<Game>().player) {
-                                    if let Some(ps) = pn.script_mut(0).and_then(|s| s.cast_mut::<Player>()) {
-                                        if !ps.game_over {
-                                            if let Some(bot_node) = ctx.scene.graph.try_get_mut(ctx.handle) {
-                                                // Ensure that bot visibility is true (Option<bool> to bool comparison)
-                                                if bot_node.visibility() == true {
-                                                    // The game is still going, so we update the bot's health
-                                                    let new_h = (self.health - 10.0).max(0.0);
-                                                    self.set_health(new_h); // <<< Enqueue the change
-                                                    println!(
-                                                        "▶ {} took damage! Pending health = {}",
-                                                        bot_node.name(),
-                                                        new_h
-                                                    );
-                                                    if let Some(target) = &self.target_handle {
-                                                        if let Some(target_node) = ctx.scene.graph.try_get_mut(*target) {
-                                                            target_node.set_visibility(true);
-                                                            println!("Target sprite visible at position: {:?}", target_node.global_position().xy());
-                                                        }
-                                                    }
-                                                    let bot_position = ctx.scene.graph[ctx.handle].global_position();
-
-                                                    let target_item_handle = ctx
-                                                        .scene
-                                                        .graph
-                                                        .pair_iter_mut()
-                                                        .find(|(_, node)| {
-                                                            node.name() == "TargetItem"
-                                                                && node.visibility()
-                                                                && (node.global_position() - bot_position).norm_squared() < f32::EPSILON
-                                                        })
-                                                        .map(|(handle, _)| handle);
-
-                                                    // If there is no existing target item, create one
-                                                    if target_item_handle.is_none() {
-                                                        // Create target item sprite (similar to spawn_target_sprite function)
-                                                        let target_item = self.spawn_target_sprite(ctx);
-                                                        self.target_handle = Some(target_item);
-                                                        println!("▶ Target item spawned at position: {:?}", ctx.scene.graph[target_item].global_position().xy());
-                                                    }
-                                                } else {
-                                                    println!("▶ Bot is not visible, no damage dealt to the Bot.");
-                                                }
-                                            }
-                                        } else {
-                                            println!("Player is in game over state, no health change.");
-                                        }
-                                    }
-                                }
-                                
-                            }
-                            if released {
-                                // Shift is released — delete the target node
-                                if let Some(target) = self.target_handle.take() {
-                                    if let Some(target_node) = ctx.scene.graph.try_get_mut(target) {
-                                        ctx.scene.graph.remove_node(target);
-                                        println!("▶ Previous target sprite removed.");
-                                    }
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
+    }
+
+    /// Blows the bot up, hurting the player `distance` away if within `radius`. The bot dies in
+    /// the blast.
+    fn explode(&mut self, radius: f32, distance: f32, ctx: &mut TickContext) {
+        if distance <= radius {
+            ctx.game.events.publish(DamageDealt {
+                target: self.target,
+                source: ctx.handle,
+                amount: self.archetype.damage,
+                kind: DamageType::Explosion,
+            });
+        }
+        println!("▶ {} exploded!", self.archetype.name);
+        if let Some(change) = self.health.set(0.0) {
+            self.apply_health_change(change, ctx);
+        }
+    }
+
+    /// Tints the sprite and swaps its sheet as the archetype says.
+    fn apply_sprite(&mut self, ctx: &mut TickContext) {
+        let sprite = &self.archetype.sprite;
+        if let Some(sheet) = &sprite.sheet {
+            let texture = ctx.resource_manager.request::<Texture>(sheet);
+            for animation in &mut self.animations {
+                animation.set_texture(Some(texture.clone()));
+            }
+        }
+        if let Some(rect) = ctx
+            .scene
+            .graph
+            .try_get_mut(*self.rectangle)
+            .and_then(|n| n.cast_mut::<Rectangle>())
+        {
+            rect.set_color(sprite.color());
+        }
+    }
+
+    /// Shows the hit marker over the bot, reusing the one already there if any.
+    fn show_hit_marker(&mut self, ctx: &mut TickContext) {
+        if let Some(target) = &self.target_handle {
+            if let Some(target_node) = ctx.scene.graph.try_get_mut(*target) {
+                target_node.set_visibility(true);
+                println!(
+                    "Target sprite visible at position: {:?}",
+                    target_node.global_position().xy()
+                );
+            }
+        }
+        let bot_position = ctx.scene.graph[ctx.handle].global_position();
+
+        let target_item_handle = ctx
+            .scene
+            .graph
+            .pair_iter_mut()
+            .find(|(_, node)| {
+                node.name() == "TargetItem"
+                    && node.visibility()
+                    && (node.global_position() - bot_position).norm_squared() < f32::EPSILON
+            })
+            .map(|(handle, _)| handle);
+
+        // If there is no existing target item, create one
+        if target_item_handle.is_none() {
+            let target_item = self.spawn_target_sprite(ctx);
+            self.target_handle = Some(target_item);
+            println!(
+                "▶ Target item spawned at position: {:?}",
+                ctx.scene.graph[target_item].global_position().xy()
+            );
+        }
+    }
+}
+
+impl Damageable for Bot {
+    fn health(&self) -> &Health {
+        &self.health
+    }
+
+    fn health_mut(&mut self) -> &mut Health {
+        &mut self.health
+    }
+
+    fn on_damaged(&mut self, hit: &DamageDealt, ctx: &mut TickContext) {
+        self.hit_marker_pending = true;
+        self.update_health_bar(ctx);
+        if let Some(bot_node) = ctx.scene.graph.try_get(ctx.handle) {
+            println!(
+                "▶ {} took {} damage! Health = {}",
+                bot_node.name(),
+                hit.amount,
+                self.health.current()
+            );
+        }
+    }
+
+    fn on_death(&mut self, ctx: &mut TickContext) {
+        self.current_animation.set_value_and_mark_modified(1); // Play death animation
+        ctx.game.events.publish(EntityDied {
+            entity: ctx.handle,
+            kind: EntityKind::Bot,
+        });
+
+        if !self.respawn {
+            if let Some(target) = self.target_handle.take() {
+                if ctx.scene.graph.is_valid_handle(target) {
+                    ctx.scene.graph.remove_node(target);
                 }
             }
+            ctx.scene.graph.remove_node(ctx.handle);
+            return;
+        }
+
+        if let Some(n) = ctx.scene.graph.try_get_mut(ctx.handle) {
+            n.set_visibility(false);
+        }
+        // Initialize the respawn timer
+        self.respawn_timer = Some(0.0);
+    }
+
+    fn on_revive(&mut self, ctx: &mut TickContext) {
+        self.respawn_timer = None;
+        // Get the player's position first (immutable borrow). Without a player, the bot
+        // respawns around the spot it died on.
+        let player_pos = ctx
+            .scene
+            .graph
+            .try_get(self.target)
+            .or_else(|| ctx.scene.graph.try_get(ctx.handle))
+            .map(|n| n.global_position().xy())
+            .unwrap_or_default();
+
+        if let Some(n) = ctx.scene.graph.try_get_mut(ctx.handle) {
+            let rng = &mut ctx.game.rng;
+            let offset_x: f32 = rng.gen_range(-5.0..=5.0);
+            let offset_y: f32 = rng.gen_range(-5.0..=5.0);
+
+            let position = ctx.game.arena.clamp(Vector2::new(
+                player_pos.x + offset_x,
+                player_pos.y + offset_y,
+            ));
+
+            // Set new random position near the player
+            n.local_transform_mut()
+                .set_position(Vector3::new(position.x, position.y, 0.0));
+            n.set_visibility(true);
+
+            println!(
+                "▶ {:?} respawned at ({:.2}, {:.2})",
+                n.name(),
+                position.x,
+                position.y
+            );
+        }
+        // The body fell asleep while the bot was dead; it would ignore the chase velocity,
+        // which is unchanged since the death.
+        if let Some(rb) = ctx.scene.graph.try_get_mut_of_type::<RigidBody>(ctx.handle) {
+            rb.wake_up();
+        }
+
+        self.update_health_bar(ctx);
+    }
+}
+
+impl ScriptTrait for Bot {
+    fn on_start(&mut self, ctx: &mut ScriptContext) {
+        self.start(&mut TickContext::from_script(ctx));
+    }
+
+    fn on_update(&mut self, ctx: &mut ScriptContext) {
+        let mut ctx = TickContext::from_script(ctx);
+        if ctx.game.is_running() {
+            self.update(&mut ctx);
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,225 @@
+//! Player combat: melee swings and dodging.
+//!
+//! A swing damages every bot inside a [`Hitbox`] in front of the player, once per swing. Swings
+//! are chained into a [`Combo`]: pressing attack again shortly after a swing plays the next,
+//! stronger [`MeleeAttack`]. A [`Dodge`] slides the player out of the way and ignores all
+//! damage for a moment. The player script owns these settings and their state.
+
+use crate::bot::Bot;
+use crate::event::{DamageDealt, EventBus};
+use crate::health::DamageType;
+use fyrox::{
+    asset::manager::ResourceManager,
+    core::{algebra::Vector2, pool::Handle, reflect::prelude::*, visitor::prelude::*},
+    graph::SceneGraph,
+    gui::texture::Texture,
+    scene::{animation::spritesheet::SpriteSheetAnimation, graph::Graph, node::Node},
+};
+
+/// Sprite sheet holding every frame of the player character.
+pub const PLAYER_SHEET: &str = "data/characters/adventurer/adventurer-Sheet.png";
+
+/// Layout of [`PLAYER_SHEET`] in frames, numbered row by row.
+const SHEET_COLUMNS: u32 = 7;
+const SHEET_ROWS: u32 = 11;
+
+/// Playback speed of attack animations, in frames per second.
+pub const ATTACK_FPS: f32 = 15.0;
+
+/// Axis-aligned box in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hitbox {
+    pub center: Vector2<f32>,
+    pub half_extents: Vector2<f32>,
+}
+
+impl Hitbox {
+    pub fn contains(&self, point: Vector2<f32>) -> bool {
+        let d = point - self.center;
+        d.x.abs() <= self.half_extents.x && d.y.abs() <= self.half_extents.y
+    }
+}
+
+/// Settings of a single swing.
+#[derive(Visit, Reflect, Debug, Clone, PartialEq)]
+pub struct MeleeAttack {
+    pub damage: f32,
+    /// How far in front of the player the hitbox reaches.
+    pub range: f32,
+    /// Height of the hitbox, centered on the player.
+    pub height: f32,
+    /// Seconds before the next swing can start.
+    pub cooldown: f32,
+    /// First and last frame of the swing animation in [`PLAYER_SHEET`].
+    pub first_frame: u32,
+    pub last_frame: u32,
+}
+
+impl Default for MeleeAttack {
+    fn default() -> Self {
+        Self {
+            damage: 10.0,
+            range: 2.0,
+            height: 2.4,
+            cooldown: 0.35,
+            first_frame: 42,
+            last_frame: 46,
+        }
+    }
+}
+
+impl MeleeAttack {
+    /// Hitbox of a swing made at `position` facing `facing` (-1 or 1 along X).
+    pub fn hitbox(&self, position: Vector2<f32>, facing: f32) -> Hitbox {
+        Hitbox {
+            center: position + Vector2::new(facing * self.range * 0.5, 0.0),
+            half_extents: Vector2::new(self.range * 0.5, self.height * 0.5),
+        }
+    }
+}
+
+/// Chain of swings played by repeated attack presses.
+#[derive(Visit, Reflect, Debug, Clone, PartialEq)]
+pub struct Combo {
+    pub attacks: Vec<MeleeAttack>,
+    /// Seconds after a swing's cooldown during which the next press continues the chain. Later
+    /// presses, and presses after the last swing, start over from the first swing.
+    pub window: f32,
+}
+
+impl Default for Combo {
+    fn default() -> Self {
+        Self {
+            attacks: vec![
+                // attack1
+                MeleeAttack::default(),
+                // attack2
+                MeleeAttack {
+                    damage: 15.0,
+                    range: 2.4,
+                    cooldown: 0.4,
+                    first_frame: 47,
+                    last_frame: 52,
+                    ..Default::default()
+                },
+                // attack3
+                MeleeAttack {
+                    damage: 25.0,
+                    range: 2.8,
+                    height: 2.8,
+                    cooldown: 0.6,
+                    first_frame: 53,
+                    last_frame: 58,
+                },
+            ],
+            window: 0.5,
+        }
+    }
+}
+
+impl Combo {
+    /// Index of the swing started by an attack press, given the previous swing and the time
+    /// elapsed since it started. `None` while the previous swing is cooling down.
+    pub fn next_step(&self, previous: Option<usize>, elapsed: f32) -> Option<usize> {
+        if self.attacks.is_empty() {
+            return None;
+        }
+        let Some(previous) = previous.filter(|&i| i < self.attacks.len()) else {
+            return Some(0);
+        };
+        let cooldown = self.attacks[previous].cooldown;
+        if elapsed < cooldown {
+            None
+        } else if elapsed <= cooldown + self.window && previous + 1 < self.attacks.len() {
+            Some(previous + 1)
+        } else {
+            Some(0)
+        }
+    }
+}
+
+/// Settings of the dodge slide.
+#[derive(Visit, Reflect, Debug, Clone, PartialEq)]
+pub struct Dodge {
+    /// Speed of the slide, in units per second.
+    pub speed: f32,
+    /// Length of the slide in seconds. Movement input is ignored meanwhile.
+    pub duration: f32,
+    /// Seconds, from the start of the slide, during which damage is ignored.
+    pub invulnerability: f32,
+    /// Seconds from the start of a slide before the next one.
+    pub cooldown: f32,
+    /// First and last frame of the slide animation in [`PLAYER_SHEET`].
+    pub first_frame: u32,
+    pub last_frame: u32,
+}
+
+impl Default for Dodge {
+    fn default() -> Self {
+        Self {
+            speed: 9.0,
+            duration: 0.3,
+            invulnerability: 0.4,
+            cooldown: 0.8,
+            first_frame: 24,
+            last_frame: 25,
+        }
+    }
+}
+
+/// Visible bots whose position lies inside `hitbox`.
+pub fn bots_in_hitbox(graph: &Graph, hitbox: &Hitbox) -> Vec<Handle<Node>> {
+    graph
+        .pair_iter()
+        .filter(|(_, node)| {
+            node.visibility()
+                && node.try_get_script::<Bot>().is_some()
+                && hitbox.contains(node.global_position().xy())
+        })
+        .map(|(handle, _)| handle)
+        .collect()
+}
+
+/// Lands a hit of `damage` from `source` on every bot inside `hitbox`, once per bot. Returns the
+/// bots that were hit.
+pub fn hit_bots(
+    graph: &Graph,
+    events: &mut EventBus,
+    source: Handle<Node>,
+    hitbox: &Hitbox,
+    damage: f32,
+) -> Vec<Handle<Node>> {
+    let hit = bots_in_hitbox(graph, hitbox);
+    for &target in &hit {
+        println!("▶ {} took {damage} damage!", graph[target].name());
+        events.publish(DamageDealt {
+            target,
+            source,
+            amount: damage,
+            kind: DamageType::Physical,
+        });
+    }
+    hit
+}
+
+/// One-shot animation made of frames `first..=last` of [`PLAYER_SHEET`], played at `fps`.
+pub fn sheet_animation(
+    resource_manager: &ResourceManager,
+    first: u32,
+    last: u32,
+    fps: f32,
+) -> SpriteSheetAnimation {
+    // Frames are added one by one: `new_from_image_parameters` computes the wrong row for
+    // sheets that are not square in frames.
+    let mut animation = SpriteSheetAnimation::new();
+    animation
+        .frames_mut()
+        .set_size(Vector2::new(SHEET_COLUMNS, SHEET_ROWS));
+    for frame in first..=last {
+        animation.add_frame(Vector2::new(frame % SHEET_COLUMNS, frame / SHEET_COLUMNS));
+    }
+    animation.set_texture(Some(resource_manager.request::<Texture>(PLAYER_SHEET)));
+    animation.set_looping(false);
+    animation.set_speed(fps);
+    animation
+}
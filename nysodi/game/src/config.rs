@@ -0,0 +1,44 @@
+//! Loading and saving of the RON files the game reads and writes next to `settings.ron`.
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt, path::Path};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    /// The file parsed, but holds values the game can't use.
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "unable to read file: {err}"),
+            ConfigError::Parse(err) => write!(f, "invalid contents: {err}"),
+            ConfigError::Serialize(err) => write!(f, "unable to serialize: {err}"),
+            ConfigError::Invalid(reason) => write!(f, "invalid value: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Reads and deserializes a RON file.
+pub fn load<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    let source = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+    parse(&source)
+}
+
+/// Deserializes RON source text.
+pub fn parse<T: DeserializeOwned>(source: &str) -> Result<T, ConfigError> {
+    ron::from_str(source).map_err(ConfigError::Parse)
+}
+
+/// Serializes a value and writes it to a RON file.
+pub fn save<T: Serialize>(path: impl AsRef<Path>, value: &T) -> Result<(), ConfigError> {
+    let source = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(ConfigError::Serialize)?;
+    std::fs::write(path, source).map_err(ConfigError::Io)
+}
@@ -0,0 +1,118 @@
+//! Per-tick context shared by the gameplay scripts.
+
+use crate::{bot::Bot, input::Action, projectile::Projectile, Game, Player};
+use fyrox::{
+    asset::manager::ResourceManager,
+    core::pool::Handle,
+    event::{ElementState, Event, WindowEvent},
+    graph::{BaseSceneGraph, SceneGraph},
+    keyboard::{KeyCode, PhysicalKey},
+    scene::{node::Node, Scene},
+    script::{ScriptContext, ScriptTrait},
+};
+
+/// Everything a gameplay script needs during one tick.
+///
+/// Scripts run their logic against this context instead of [`ScriptContext`], so the same code
+/// runs inside the engine and in the headless [`Simulation`](crate::sim::Simulation).
+pub struct TickContext<'a> {
+    pub dt: f32,
+    /// Node the running script is attached to.
+    pub handle: Handle<Node>,
+    pub scene: &'a mut Scene,
+    pub game: &'a mut Game,
+    pub resource_manager: &'a ResourceManager,
+}
+
+impl<'a> TickContext<'a> {
+    pub fn from_script(ctx: &'a mut ScriptContext) -> Self {
+        Self {
+            dt: ctx.dt,
+            handle: ctx.handle,
+            scene: &mut *ctx.scene,
+            game: ctx.plugins.get_mut::<Game>(),
+            resource_manager: ctx.resource_manager,
+        }
+    }
+}
+
+/// Extracts the key code and pressed state of a keyboard event.
+pub fn keyboard_input(event: &Event<()>) -> Option<(KeyCode, bool)> {
+    let Event::WindowEvent {
+        event: WindowEvent::KeyboardInput { event, .. },
+        ..
+    } = event
+    else {
+        return None;
+    };
+    let PhysicalKey::Code(key) = event.physical_key else {
+        return None;
+    };
+    Some((key, event.state == ElementState::Pressed))
+}
+
+/// Runs `f` on a copy of the script of type `S` attached to `handle` and stores the result
+/// back, which mirrors how the engine takes a script out of its node while it runs. Used to run
+/// scripts from outside the engine's script processing.
+pub fn run_script<S>(
+    scene: &mut Scene,
+    game: &mut Game,
+    resource_manager: &ResourceManager,
+    dt: f32,
+    handle: Handle<Node>,
+    f: impl FnOnce(&mut S, &mut TickContext),
+) where
+    S: ScriptTrait + Clone,
+{
+    let Some(mut script) = scene
+        .graph
+        .try_get(handle)
+        .and_then(|n| n.try_get_script::<S>())
+        .cloned()
+    else {
+        return;
+    };
+    let mut ctx = TickContext {
+        dt,
+        handle,
+        scene: &mut *scene,
+        game: &mut *game,
+        resource_manager,
+    };
+    f(&mut script, &mut ctx);
+    if let Some(slot) = scene
+        .graph
+        .try_get_mut(handle)
+        .and_then(|n| n.try_get_script_mut::<S>())
+    {
+        *slot = script;
+    }
+}
+
+/// Handles of every node running a gameplay script.
+pub fn script_nodes(scene: &Scene) -> Vec<Handle<Node>> {
+    scene
+        .graph
+        .pair_iter()
+        .filter(|(_, n)| {
+            n.has_script::<Player>() || n.has_script::<Bot>() || n.has_script::<Projectile>()
+        })
+        .map(|(h, _)| h)
+        .collect()
+}
+
+/// Feeds an action press or release to the player scripts of the scene.
+pub fn dispatch_action(
+    scene: &mut Scene,
+    game: &mut Game,
+    resource_manager: &ResourceManager,
+    dt: f32,
+    action: Action,
+    pressed: bool,
+) {
+    for handle in script_nodes(scene) {
+        run_script::<Player>(scene, game, resource_manager, dt, handle, |s, ctx| {
+            s.handle_action(action, pressed, ctx)
+        });
+    }
+}
@@ -0,0 +1,177 @@
+//! Data-driven enemy archetypes.
+//!
+//! Every kind of enemy (skeleton, runner, tank, bomber, ...) is the same [`Bot`](crate::bot::Bot)
+//! script tuned by an [`Archetype`] loaded from `enemies.ron`, which lives next to
+//! `settings.ron`. Waves pick the archetype of each bot they spawn; a bot placed in the scene
+//! without one behaves like the [default](Archetype::default) skeleton. Archetypes with
+//! [phases](crate::boss::BossPhase) are bosses.
+
+use crate::{
+    boss::BossPhase,
+    config::{self, ConfigError},
+    projectile::ProjectileSpec,
+};
+use fyrox::core::{color::Color, reflect::prelude::*, visitor::prelude::*};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Location of the enemy archetypes, relative to the working directory of the game.
+pub const ENEMIES_PATH: &str = "enemies.ron";
+
+/// How a bot hurts the player once in range.
+#[derive(Deserialize, Visit, Reflect, Debug, Clone, PartialEq, Default)]
+pub enum Attack {
+    /// Hits the player every `cooldown` seconds.
+    #[default]
+    Melee,
+    /// Blows up, dealing its damage once to the player if closer than `radius`.
+    Explode { radius: f32 },
+    /// Keeps at least `keep_distance` away from the player and shoots a `projectile` at them
+    /// every `cooldown` seconds while in range.
+    Ranged {
+        keep_distance: f32,
+        projectile: ProjectileSpec,
+    },
+}
+
+/// Look of a bot.
+#[derive(Deserialize, Visit, Reflect, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Sprite {
+    /// Sprite sheet replacing the one of the prefab. It must have the same frame layout.
+    pub sheet: Option<String>,
+    /// Colour multiplied with the sprite, written `(red, green, blue)`.
+    pub tint: [u8; 3],
+    pub scale: f32,
+}
+
+impl Default for Sprite {
+    fn default() -> Self {
+        Self {
+            sheet: None,
+            tint: [255, 255, 255],
+            scale: 2.0,
+        }
+    }
+}
+
+impl Sprite {
+    pub fn color(&self) -> Color {
+        let [r, g, b] = self.tint;
+        Color::opaque(r, g, b)
+    }
+}
+
+/// Describes a single kind of enemy.
+#[derive(Deserialize, Visit, Reflect, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Archetype {
+    /// Unique name of the archetype, also used as the name of spawned bots.
+    pub name: String,
+    /// Chase speed, in units per second.
+    pub speed: f32,
+    pub health: f32,
+    /// Damage of a melee hit, of the explosion or of a projectile.
+    pub damage: f32,
+    /// The bot attacks when the player is closer than this distance.
+    pub attack_range: f32,
+    /// Seconds in range before each melee hit or shot.
+    pub cooldown: f32,
+    pub attack: Attack,
+    /// The bot loses sight of a player further than this and patrols until it comes closer.
+    /// Without one, the bot always knows where the player is.
+    pub sight: Option<f32>,
+    pub sprite: Sprite,
+    /// Phases of a boss, by decreasing health threshold. Empty for regular enemies.
+    pub phases: Vec<BossPhase>,
+}
+
+impl Default for Archetype {
+    fn default() -> Self {
+        Self {
+            name: "Skeleton".to_owned(),
+            speed: 1.2,
+            health: 100.0,
+            damage: 20.0,
+            attack_range: 1.5,
+            cooldown: 0.75,
+            attack: Attack::Melee,
+            sight: None,
+            sprite: Sprite::default(),
+            phases: Vec::new(),
+        }
+    }
+}
+
+impl Archetype {
+    /// Checks the values a bot can't fight with: health, speed, attack range and cooldown must
+    /// be positive, damage and sight not negative.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |what: &str| ConfigError::Invalid(format!("archetype {}: {what}", self.name));
+        // NaN counts as out of range.
+        let negative = |value: f32| value.is_nan() || value < 0.0;
+        let not_positive = |value: f32| value.is_nan() || value <= 0.0;
+        if not_positive(self.health) {
+            return Err(invalid("health must be greater than 0"));
+        }
+        if not_positive(self.speed) {
+            return Err(invalid("speed must be greater than 0"));
+        }
+        if not_positive(self.attack_range) {
+            return Err(invalid("attack_range must be greater than 0"));
+        }
+        if not_positive(self.cooldown) {
+            return Err(invalid("cooldown must be greater than 0"));
+        }
+        if negative(self.damage) {
+            return Err(invalid("damage must not be negative"));
+        }
+        if self.sight.is_some_and(negative) {
+            return Err(invalid("sight must not be negative"));
+        }
+        Ok(())
+    }
+}
+
+/// All enemy archetypes known to the game.
+#[derive(Debug, Default)]
+pub struct ArchetypeRegistry {
+    archetypes: Vec<Archetype>,
+}
+
+impl ArchetypeRegistry {
+    pub fn new(archetypes: Vec<Archetype>) -> Self {
+        Self { archetypes }
+    }
+
+    /// Reads the archetypes at `path`. Fails on the first archetype that doesn't
+    /// [validate](Archetype::validate), or that reuses the name of another one.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        config::load(path).and_then(Self::validated)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ConfigError> {
+        config::parse(source).and_then(Self::validated)
+    }
+
+    fn validated(archetypes: Vec<Archetype>) -> Result<Self, ConfigError> {
+        for (index, archetype) in archetypes.iter().enumerate() {
+            archetype.validate()?;
+            if archetypes[..index].iter().any(|a| a.name == archetype.name) {
+                return Err(ConfigError::Invalid(format!(
+                    "archetype {} is defined twice",
+                    archetype.name
+                )));
+            }
+        }
+        Ok(Self::new(archetypes))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Archetype> {
+        self.archetypes.iter().find(|a| a.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Archetype> {
+        self.archetypes.iter()
+    }
+}
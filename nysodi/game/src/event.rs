@@ -0,0 +1,268 @@
+//! Typed event bus for gameplay events.
+//!
+//! The [`Game`](crate::Game) plugin owns an [`EventBus`]. Scripts and systems publish what
+//! happened (a hit landed, a bot died, an item was picked up) instead of mutating whoever cares
+//! about it, and anyone interested reads the events back:
+//!
+//! - scripts keep an [`EventReader`] per event type and call [`EventBus::read`] every tick,
+//!   which returns the events published since their previous read;
+//! - plugins and tools register callbacks with [`EventBus::subscribe`], called with every event
+//!   once per frame, when the game calls [`EventBus::update`].
+//!
+//! Events are kept for two frames, so a reader running once per frame never misses one whatever
+//! order scripts run in.
+
+use crate::health::DamageType;
+use fyrox::core::pool::Handle;
+use fyrox::scene::node::Node;
+use std::{
+    any::{Any, TypeId},
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+
+/// Anything that can be published on the [`EventBus`].
+pub trait Event: Any + Debug {}
+
+/// A hit landing on `target`. The target applies the damage itself, unless it ignores it (e.g.
+/// an invulnerable player).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DamageDealt {
+    pub target: Handle<Node>,
+    /// Node dealing the damage, [`Handle::NONE`] for items and debug damage.
+    pub source: Handle<Node>,
+    pub amount: f32,
+    pub kind: DamageType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+    Player,
+    Bot,
+}
+
+/// Health of an entity reached zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityDied {
+    pub entity: Handle<Node>,
+    pub kind: EntityKind,
+}
+
+/// The player picked up an item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemCollected {
+    /// Name of the item definition.
+    pub item: String,
+    pub collector: Handle<Node>,
+}
+
+/// A bot was added to the scene by the wave director.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BotSpawned {
+    pub bot: Handle<Node>,
+}
+
+/// A projectile was fired.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectileFired {
+    pub projectile: Handle<Node>,
+    /// Node that fired it.
+    pub owner: Handle<Node>,
+}
+
+/// A boss moved on to its next phase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BossPhaseChanged {
+    pub boss: Handle<Node>,
+    /// Index of the new phase in the archetype of the boss.
+    pub phase: u32,
+}
+
+/// The player was defeated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameOver {
+    pub score: f32,
+}
+
+impl Event for DamageDealt {}
+impl Event for EntityDied {}
+impl Event for ItemCollected {}
+impl Event for BotSpawned {}
+impl Event for ProjectileFired {}
+impl Event for BossPhaseChanged {}
+impl Event for GameOver {}
+
+/// Position of a reader in the events of type `E`.
+pub struct EventReader<E> {
+    next: u64,
+    marker: PhantomData<fn() -> E>,
+}
+
+impl<E> Default for EventReader<E> {
+    fn default() -> Self {
+        Self {
+            next: 0,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<E> Clone for EventReader<E> {
+    fn clone(&self) -> Self {
+        Self {
+            next: self.next,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<E> Debug for EventReader<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventReader")
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+type Subscriber<E> = Box<dyn FnMut(&E)>;
+
+struct Channel<E> {
+    /// Events of the previous and the current frame, oldest first.
+    events: Vec<E>,
+    /// Id of `events[0]`. Ids keep growing over the whole session.
+    first_id: u64,
+    /// Index in `events` of the first event of the current frame.
+    frame_start: usize,
+    subscribers: Vec<Subscriber<E>>,
+}
+
+impl<E> Default for Channel<E> {
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            first_id: 0,
+            frame_start: 0,
+            subscribers: Vec::new(),
+        }
+    }
+}
+
+/// Type-erased [`Channel`], so channels of every event type share one list.
+trait AnyChannel {
+    fn update(&mut self);
+    fn clear(&mut self);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<E: Event> AnyChannel for Channel<E> {
+    fn update(&mut self) {
+        for event in &self.events[self.frame_start..] {
+            for subscriber in &mut self.subscribers {
+                subscriber(event);
+            }
+        }
+        self.events.drain(..self.frame_start);
+        self.first_id += self.frame_start as u64;
+        self.frame_start = self.events.len();
+    }
+
+    fn clear(&mut self) {
+        self.first_id += self.events.len() as u64;
+        self.events.clear();
+        self.frame_start = 0;
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    /// One channel per event type, in the order the types were first used.
+    channels: Vec<(TypeId, Box<dyn AnyChannel>)>,
+}
+
+impl Debug for EventBus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventBus")
+            .field("channels", &self.channels.len())
+            .finish()
+    }
+}
+
+impl EventBus {
+    fn channel<E: Event>(&self) -> Option<&Channel<E>> {
+        self.channels
+            .iter()
+            .find(|(id, _)| *id == TypeId::of::<E>())
+            .and_then(|(_, c)| c.as_any().downcast_ref())
+    }
+
+    fn channel_mut<E: Event>(&mut self) -> &mut Channel<E> {
+        let index = match self
+            .channels
+            .iter()
+            .position(|(id, _)| *id == TypeId::of::<E>())
+        {
+            Some(index) => index,
+            None => {
+                self.channels
+                    .push((TypeId::of::<E>(), Box::new(Channel::<E>::default())));
+                self.channels.len() - 1
+            }
+        };
+        self.channels[index]
+            .1
+            .as_any_mut()
+            .downcast_mut()
+            .expect("channel is keyed by its event type")
+    }
+
+    pub fn publish<E: Event>(&mut self, event: E) {
+        self.channel_mut::<E>().events.push(event);
+    }
+
+    /// Calls `subscriber` with every event of type `E` published from now on, once per frame.
+    pub fn subscribe<E: Event>(&mut self, subscriber: impl FnMut(&E) + 'static) {
+        self.channel_mut::<E>()
+            .subscribers
+            .push(Box::new(subscriber));
+    }
+
+    /// Events of type `E` published since the previous read with `reader`.
+    pub fn read<'a, E: Event>(
+        &'a self,
+        reader: &mut EventReader<E>,
+    ) -> impl Iterator<Item = &'a E> + 'a {
+        let events = match self.channel::<E>() {
+            Some(channel) => {
+                let start = reader.next.saturating_sub(channel.first_id) as usize;
+                reader.next = channel.first_id + channel.events.len() as u64;
+                &channel.events[start.min(channel.events.len())..]
+            }
+            None => &[],
+        };
+        events.iter()
+    }
+
+    /// Ends the frame: hands the events of the frame to the subscribers and drops the events of
+    /// the frame before.
+    pub fn update(&mut self) {
+        for (_, channel) in &mut self.channels {
+            channel.update();
+        }
+    }
+
+    /// Drops every pending event, e.g. when the scene is replaced. Subscribers are kept.
+    pub fn clear(&mut self) {
+        for (_, channel) in &mut self.channels {
+            channel.clear();
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Local avoidance between bots.
+//!
+//! Bots chasing the player would all head for the same point and pile up in a single blob.
+//! [`Flock`] spreads them out: every hunting bot gets a slot on a circle around the player to
+//! walk to, and bots coming too close to each other push apart. The [`Game`](crate::Game)
+//! updates the flock once per tick, before the bots move.
+
+use crate::{ai::AiState, bot::Bot};
+use fyrox::{
+    core::{algebra::Vector2, pool::Handle},
+    graph::{BaseSceneGraph, SceneGraph},
+    scene::{graph::Graph, node::Node},
+};
+use std::f32::consts::TAU;
+
+/// Bots closer than this push each other away.
+pub const SEPARATION_RADIUS: f32 = 1.0;
+
+/// Hunting bots and their slots around the player.
+#[derive(Debug, Clone, Default)]
+pub struct Flock {
+    /// Position of the player.
+    center: Vector2<f32>,
+    /// Visible bots chasing or attacking the player, and their position.
+    members: Vec<(Handle<Node>, Vector2<f32>)>,
+    /// Direction of the slot of each member, as an angle around the player.
+    slots: Vec<(Handle<Node>, f32)>,
+}
+
+impl Flock {
+    /// Gathers the bots hunting `player` and hands out the slots around it, evenly spaced. The
+    /// closest bots pick first, each taking the free slot closest to where it comes from.
+    pub fn update(&mut self, graph: &Graph, player: Handle<Node>) {
+        self.members.clear();
+        self.slots.clear();
+        let Some(player) = graph.try_get(player) else {
+            return;
+        };
+        self.center = player.global_position().xy();
+        self.members = graph
+            .pair_iter()
+            .filter(|(_, node)| node.visibility())
+            .filter_map(|(handle, node)| {
+                let bot = node.try_get_script::<Bot>()?;
+                matches!(bot.state(), AiState::Chase | AiState::Attack)
+                    .then(|| (handle, node.global_position().xy()))
+            })
+            .collect();
+
+        let mut order = self.members.clone();
+        order.sort_by(|a, b| {
+            let distance = |p: Vector2<f32>| (p - self.center).norm_squared();
+            distance(a.1).total_cmp(&distance(b.1))
+        });
+        let Some(&(_, closest)) = order.first() else {
+            return;
+        };
+        let angle = |position: Vector2<f32>| {
+            let offset = position - self.center;
+            offset.y.atan2(offset.x)
+        };
+        let step = TAU / order.len() as f32;
+        let first = angle(closest);
+        let mut free: Vec<f32> = (0..order.len()).map(|i| first + i as f32 * step).collect();
+        for (bot, position) in order {
+            let from = angle(position);
+            let Some(slot) = (0..free.len()).min_by(|&a, &b| {
+                angle_between(free[a], from).total_cmp(&angle_between(free[b], from))
+            }) else {
+                break;
+            };
+            self.slots.push((bot, free.swap_remove(slot)));
+        }
+    }
+
+    /// Point `distance` away from the player where `bot` should stand, if it hunts.
+    pub fn slot(&self, bot: Handle<Node>, distance: f32) -> Option<Vector2<f32>> {
+        let &(_, angle) = self.slots.iter().find(|(handle, _)| *handle == bot)?;
+        Some(self.center + Vector2::new(angle.cos(), angle.sin()) * distance)
+    }
+
+    /// Push away from the other hunting bots within [`SEPARATION_RADIUS`] of `position`,
+    /// stronger the closer they are. Zero when `bot` has room around it.
+    pub fn separation(&self, bot: Handle<Node>, position: Vector2<f32>) -> Vector2<f32> {
+        self.members
+            .iter()
+            .filter(|(handle, _)| *handle != bot)
+            .filter_map(|&(handle, other)| {
+                let offset = position - other;
+                let distance = offset.norm();
+                if distance >= SEPARATION_RADIUS {
+                    return None;
+                }
+                // Bots on the very same spot part along the x axis, in handle order.
+                let away = offset.try_normalize(f32::EPSILON).unwrap_or_else(|| {
+                    if bot.index() < handle.index() {
+                        -Vector2::x()
+                    } else {
+                        Vector2::x()
+                    }
+                });
+                Some(away * (1.0 - distance / SEPARATION_RADIUS))
+            })
+            .sum()
+    }
+}
+
+/// Unsigned difference between the angles `a` and `b`, in radians.
+fn angle_between(a: f32, b: f32) -> f32 {
+    let difference = (a - b).rem_euclid(TAU);
+    difference.min(TAU - difference)
+}
@@ -0,0 +1,501 @@
+//! Procedural arenas.
+//!
+//! A level can have its arena generated instead of drawn by hand, with the [`GeneratorSettings`]
+//! under `generate` in `levels.ron`. [`Layout::generate`] lays out a walled floor with obstacles
+//! scattered over it, the start of the player and the spawn points of the waves. An obstacle
+//! walling off part of the floor is taken back, so the player can walk to every floor cell.
+//!
+//! [`Layout::build`] then replaces the tile maps and walls of the scene of the level with the
+//! layout: tile maps drawn with the tiles of a [`TilePalette`], a static collider over every row
+//! of wall and obstacle cells, the arena and spawn point markers read by the
+//! [`Game`](crate::Game), and the player moved to the start.
+//! The same seed always gives the same arena.
+//!
+//! Tiles carry no collision: the tile sets of the game have no collider layers, so walls get
+//! static bodies of their own, like the walls of the hand-made scene.
+
+use crate::{
+    arena::{ARENA_NODE, SPAWN_POINT_NODE},
+    Player,
+};
+use fyrox::{
+    asset::manager::ResourceManager,
+    core::{
+        algebra::{Vector2, Vector3},
+        pool::Handle,
+    },
+    graph::{BaseSceneGraph, SceneGraph},
+    rand::{seq::SliceRandom, Rng},
+    scene::{
+        base::BaseBuilder,
+        dim2::{
+            collider::{ColliderBuilder, ColliderShape},
+            rigidbody::{RigidBody, RigidBodyBuilder},
+        },
+        graph::Graph,
+        node::Node,
+        pivot::PivotBuilder,
+        rigidbody::RigidBodyType,
+        tilemap::{tileset::TileSet, TileDefinitionHandle, TileMap, TileMapBuilder, Tiles},
+        transform::TransformBuilder,
+        Scene,
+    },
+};
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+/// Name of the node holding everything generated.
+pub const GENERATED_NODE: &str = "Generated Arena";
+/// Smallest arena generated, walls included, in both directions.
+pub const MIN_SIZE: u32 = 8;
+/// Cells around the start of the player kept free of obstacles.
+const START_CLEARANCE: i32 = 2;
+/// Largest side of an obstacle, in cells.
+const MAX_OBSTACLE_SIZE: i32 = 3;
+/// Distance kept between spawn points, in cells.
+const SPAWN_SPACING: f32 = 4.0;
+/// Distance kept between the start and the spawn points, in cells, in arenas large enough.
+const SPAWN_DISTANCE: f32 = 6.0;
+/// Depth of the floor and wall tile maps, like the tile maps of the hand-made scene.
+const FLOOR_DEPTH: f32 = 4.0;
+const WALL_DEPTH: f32 = 3.0;
+
+fn default_obstacles() -> f32 {
+    0.1
+}
+
+fn default_spawn_points() -> u32 {
+    4
+}
+
+/// Tile of a tile set: `(page_x, page_y, tile_x, tile_y)`.
+pub type TileRef = (i16, i16, i16, i16);
+
+/// How to generate the arena of a level.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct GeneratorSettings {
+    /// Seed of the arena. Derived from the seed of the run and the level if absent, so every
+    /// run plays a different arena.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Width and height of the arena in tiles, walls included.
+    pub size: (u32, u32),
+    /// Share of the floor covered by obstacles.
+    #[serde(default = "default_obstacles")]
+    pub obstacles: f32,
+    /// Spawn points of the waves. Small arenas may fit fewer.
+    #[serde(default = "default_spawn_points")]
+    pub spawn_points: u32,
+    #[serde(default)]
+    pub tiles: TilePalette,
+}
+
+/// Tiles an arena is drawn with. Every cell gets one of the tiles of its kind, picked at random.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct TilePalette {
+    /// Path of the tile set holding the tiles.
+    pub tile_set: String,
+    pub floor: Vec<TileRef>,
+    pub wall: Vec<TileRef>,
+    pub obstacle: Vec<TileRef>,
+}
+
+impl Default for TilePalette {
+    /// Ground, wall and crate tiles of the tile set of the hand-made scene.
+    fn default() -> Self {
+        Self {
+            tile_set: "data/tiles/tileset_formation.tileset".to_owned(),
+            floor: vec![(1, -2, 0, -1), (1, -2, 1, -1)],
+            wall: vec![
+                (-4, -2, 2, -5),
+                (8, -5, 12, -9),
+                (3, -4, 11, -17),
+                (3, -4, 11, -18),
+            ],
+            obstacle: vec![
+                (-8, 2, 24, -5),
+                (-8, 2, 24, -6),
+                (-8, 2, 25, -5),
+                (-8, 2, 25, -6),
+            ],
+        }
+    }
+}
+
+/// Seed of the arena of `level` in a run started from `run_seed`.
+pub fn level_seed(run_seed: u64, level: u32) -> u64 {
+    run_seed ^ (u64::from(level) + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+/// What a cell of a layout holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Floor,
+    Wall,
+    Obstacle,
+}
+
+impl Cell {
+    pub fn is_solid(self) -> bool {
+        self != Cell::Floor
+    }
+}
+
+/// Grid of one-unit cells making an arena, surrounded by walls and centered on the origin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub width: i32,
+    pub height: i32,
+    cells: Vec<Cell>,
+    /// Cell the player starts on.
+    pub start: Vector2<i32>,
+    /// Cells the waves spawn their bots on.
+    pub spawn_points: Vec<Vector2<i32>>,
+}
+
+impl Layout {
+    /// Floor walled on every side, without obstacles or spawn points. The player starts in the
+    /// middle.
+    pub fn open(width: u32, height: u32) -> Self {
+        let (width, height) = (width.max(MIN_SIZE) as i32, height.max(MIN_SIZE) as i32);
+        let cells = (0..height)
+            .flat_map(|y| {
+                (0..width).map(move |x| {
+                    if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                        Cell::Wall
+                    } else {
+                        Cell::Floor
+                    }
+                })
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            cells,
+            start: Vector2::new(width / 2, height / 2),
+            spawn_points: Vec::new(),
+        }
+    }
+
+    /// Random arena of `settings`.
+    pub fn generate(settings: &GeneratorSettings, rng: &mut impl Rng) -> Self {
+        let (width, height) = settings.size;
+        let mut layout = Self::open(width, height);
+        layout.scatter_obstacles(settings.obstacles, rng);
+        layout.place_spawn_points(settings.spawn_points, rng);
+        layout
+    }
+
+    /// What `cell` holds. Everything around the layout is wall.
+    pub fn cell(&self, cell: Vector2<i32>) -> Cell {
+        self.index(cell)
+            .map_or(Cell::Wall, |index| self.cells[index])
+    }
+
+    pub fn set(&mut self, cell: Vector2<i32>, content: Cell) {
+        if let Some(index) = self.index(cell) {
+            self.cells[index] = content;
+        }
+    }
+
+    /// Whether the player can walk from the start to every floor cell, without squeezing
+    /// diagonally between two solid cells.
+    pub fn is_connected(&self) -> bool {
+        let reached = self.reachable();
+        self.cells
+            .iter()
+            .zip(reached)
+            .all(|(&cell, reached)| cell != Cell::Floor || reached)
+    }
+
+    /// World position of the corner of the cell `(0, 0)`.
+    pub fn origin(&self) -> Vector2<f32> {
+        self.origin_cell().cast()
+    }
+
+    /// World position of the center of `cell`.
+    pub fn world_position(&self, cell: Vector2<i32>) -> Vector2<f32> {
+        self.origin() + cell.cast::<f32>() + Vector2::repeat(0.5)
+    }
+
+    /// Replaces the arena of `scene`, hand-made or generated, with this layout, drawn with the
+    /// tiles of `palette`, and moves the player to the start. Returns the node holding the new
+    /// arena.
+    pub fn build(
+        &self,
+        scene: &mut Scene,
+        palette: &TilePalette,
+        resource_manager: &ResourceManager,
+        rng: &mut impl Rng,
+    ) -> Handle<Node> {
+        let graph = &mut scene.graph;
+        clear_arena(graph);
+
+        let (mut floor, mut walls) = (Tiles::default(), Tiles::default());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let cell = Vector2::new(x, y);
+                // Tile maps flip the x axis: the tile at `p` covers x from -(p.x + 1) to -p.x.
+                let corner = self.origin_cell() + cell;
+                let position = Vector2::new(-corner.x - 1, corner.y);
+                if let Some(tile) = pick(&palette.floor, rng) {
+                    floor.insert(position, tile);
+                }
+                let tiles = match self.cell(cell) {
+                    Cell::Floor => continue,
+                    Cell::Wall => &palette.wall,
+                    Cell::Obstacle => &palette.obstacle,
+                };
+                if let Some(tile) = pick(tiles, rng) {
+                    walls.insert(position, tile);
+                }
+            }
+        }
+        let tile_set = resource_manager.request::<TileSet>(&palette.tile_set);
+        let mut children = vec![
+            TileMapBuilder::new(at(
+                BaseBuilder::new().with_name("Floor"),
+                Vector2::zeros(),
+                FLOOR_DEPTH,
+            ))
+            .with_tile_set(tile_set.clone())
+            .with_tiles(&floor)
+            .build(graph),
+            TileMapBuilder::new(at(
+                BaseBuilder::new().with_name("Walls"),
+                Vector2::zeros(),
+                WALL_DEPTH,
+            ))
+            .with_tile_set(tile_set)
+            .with_tiles(&walls)
+            .build(graph),
+        ];
+
+        // One body per row of solid cells keeps the physics and the navigation grid cheap.
+        for y in 0..self.height {
+            let mut x = 0;
+            while x < self.width {
+                let first = x;
+                while x < self.width && self.cell(Vector2::new(x, y)).is_solid() {
+                    x += 1;
+                }
+                if x == first {
+                    x += 1;
+                    continue;
+                }
+                let length = (x - first) as f32;
+                let center =
+                    self.origin() + Vector2::new(first as f32 + length / 2.0, y as f32 + 0.5);
+                children.push(wall(graph, center, length / 2.0));
+            }
+        }
+
+        // The arena keeps half a unit away from the walls, like the arena read from walls.
+        let center = self.origin() + Vector2::new(self.width as f32, self.height as f32) / 2.0;
+        let size = Vector2::new(self.width as f32 - 3.0, self.height as f32 - 3.0);
+        children.push(
+            PivotBuilder::new(
+                BaseBuilder::new()
+                    .with_name(ARENA_NODE)
+                    .with_local_transform(
+                        TransformBuilder::new()
+                            .with_local_position(Vector3::new(center.x, center.y, 0.0))
+                            .with_local_scale(Vector3::new(size.x, size.y, 1.0))
+                            .build(),
+                    ),
+            )
+            .build(graph),
+        );
+        for &cell in &self.spawn_points {
+            let position = self.world_position(cell);
+            children.push(
+                PivotBuilder::new(at(
+                    BaseBuilder::new().with_name(SPAWN_POINT_NODE),
+                    position,
+                    0.0,
+                ))
+                .build(graph),
+            );
+        }
+
+        let player = graph
+            .pair_iter()
+            .find(|(_, node)| node.has_script::<Player>())
+            .map(|(handle, _)| handle);
+        if let Some(player) = player {
+            let start = self.world_position(self.start);
+            let transform = graph[player].local_transform_mut();
+            let depth = transform.position().z;
+            transform.set_position(Vector3::new(start.x, start.y, depth));
+        }
+
+        PivotBuilder::new(
+            BaseBuilder::new()
+                .with_name(GENERATED_NODE)
+                .with_children(&children),
+        )
+        .build(graph)
+    }
+
+    fn origin_cell(&self) -> Vector2<i32> {
+        Vector2::new(-self.width / 2, -self.height / 2)
+    }
+
+    fn index(&self, cell: Vector2<i32>) -> Option<usize> {
+        (cell.x >= 0 && cell.y >= 0 && cell.x < self.width && cell.y < self.height)
+            .then(|| (cell.y * self.width + cell.x) as usize)
+    }
+
+    fn floor_cells(&self) -> impl Iterator<Item = Vector2<i32>> + '_ {
+        (0..self.height)
+            .flat_map(move |y| (0..self.width).map(move |x| Vector2::new(x, y)))
+            .filter(|&cell| self.cell(cell) == Cell::Floor)
+    }
+
+    /// Floor cells the player can walk to from the start.
+    fn reachable(&self) -> Vec<bool> {
+        let mut reached = vec![false; self.cells.len()];
+        let mut open = VecDeque::new();
+        if let Some(start) = self
+            .index(self.start)
+            .filter(|_| self.cell(self.start) == Cell::Floor)
+        {
+            reached[start] = true;
+            open.push_back(self.start);
+        }
+        while let Some(cell) = open.pop_front() {
+            for step in [Vector2::x(), -Vector2::x(), Vector2::y(), -Vector2::y()] {
+                let next = cell + step;
+                if self.cell(next) != Cell::Floor {
+                    continue;
+                }
+                if let Some(index) = self.index(next).filter(|&index| !reached[index]) {
+                    reached[index] = true;
+                    open.push_back(next);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Covers about `share` of the floor with obstacles, away from the start. Obstacles cutting
+    /// the floor in two are taken back.
+    fn scatter_obstacles(&mut self, share: f32, rng: &mut impl Rng) {
+        let target = (self.floor_cells().count() as f32 * share.clamp(0.0, 0.5)) as usize;
+        let mut covered = 0;
+        for _ in 0..target * 4 {
+            if covered >= target {
+                break;
+            }
+            let size = Vector2::new(
+                rng.gen_range(1..=MAX_OBSTACLE_SIZE),
+                rng.gen_range(1..=MAX_OBSTACLE_SIZE),
+            );
+            let corner = Vector2::new(
+                rng.gen_range(1..=self.width - 1 - size.x),
+                rng.gen_range(1..=self.height - 1 - size.y),
+            );
+            let cells: Vec<_> = (0..size.y)
+                .flat_map(|y| (0..size.x).map(move |x| corner + Vector2::new(x, y)))
+                .collect();
+            let blocked = cells.iter().any(|&cell| {
+                self.cell(cell) != Cell::Floor || (cell - self.start).abs().max() <= START_CLEARANCE
+            });
+            if blocked {
+                continue;
+            }
+            for &cell in &cells {
+                self.set(cell, Cell::Obstacle);
+            }
+            if self.is_connected() {
+                covered += cells.len();
+            } else {
+                for &cell in &cells {
+                    self.set(cell, Cell::Floor);
+                }
+            }
+        }
+    }
+
+    /// Picks up to `count` spawn points on floor cells clear of walls and obstacles, spread out
+    /// and away from the start.
+    fn place_spawn_points(&mut self, count: u32, rng: &mut impl Rng) {
+        let inner = (self.width.min(self.height) - 2) as f32;
+        let min_distance = SPAWN_DISTANCE.min(inner / 2.0);
+        let distance = |a: Vector2<i32>, b: Vector2<i32>| (a - b).cast::<f32>().norm();
+        let mut candidates: Vec<_> = self
+            .floor_cells()
+            .filter(|&cell| {
+                let clear = (-1..=1)
+                    .flat_map(|y| (-1..=1).map(move |x| Vector2::new(x, y)))
+                    .all(|step| self.cell(cell + step) == Cell::Floor);
+                clear && distance(cell, self.start) >= min_distance
+            })
+            .collect();
+        candidates.shuffle(rng);
+        let mut spawn_points: Vec<Vector2<i32>> = Vec::new();
+        for cell in candidates {
+            if spawn_points.len() >= count as usize {
+                break;
+            }
+            if spawn_points
+                .iter()
+                .all(|&point| distance(point, cell) >= SPAWN_SPACING)
+            {
+                spawn_points.push(cell);
+            }
+        }
+        self.spawn_points = spawn_points;
+    }
+}
+
+fn pick(tiles: &[TileRef], rng: &mut impl Rng) -> Option<TileDefinitionHandle> {
+    tiles.choose(rng).map(|&(page_x, page_y, tile_x, tile_y)| {
+        TileDefinitionHandle::new(page_x, page_y, tile_x, tile_y)
+    })
+}
+
+fn at(base: BaseBuilder, position: Vector2<f32>, depth: f32) -> BaseBuilder {
+    base.with_local_transform(
+        TransformBuilder::new()
+            .with_local_position(Vector3::new(position.x, position.y, depth))
+            .build(),
+    )
+}
+
+/// Static body over a row of solid cells centered on `center`.
+fn wall(graph: &mut Graph, center: Vector2<f32>, half_length: f32) -> Handle<Node> {
+    let collider = ColliderBuilder::new(BaseBuilder::new())
+        .with_shape(ColliderShape::cuboid(half_length, 0.5))
+        .build(graph);
+    RigidBodyBuilder::new(
+        at(BaseBuilder::new().with_name("Wall"), center, 0.0).with_children(&[collider]),
+    )
+    .with_body_type(RigidBodyType::Static)
+    .build(graph)
+}
+
+/// Removes the tile maps, the static bodies without scripts, the markers and any arena generated
+/// before from `graph`.
+fn clear_arena(graph: &mut Graph) {
+    let arena: Vec<_> = graph
+        .pair_iter()
+        .filter(|(_, node)| {
+            let is_wall = node
+                .cast::<RigidBody>()
+                .is_some_and(|body| body.body_type() == RigidBodyType::Static)
+                && !node.has_scripts_assigned();
+            is_wall
+                || node.cast::<TileMap>().is_some()
+                || [ARENA_NODE, SPAWN_POINT_NODE, GENERATED_NODE].contains(&node.name())
+        })
+        .map(|(handle, _)| handle)
+        .collect();
+    for handle in arena {
+        if graph.is_valid_handle(handle) {
+            graph.remove_node(handle);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Health shared by everything that can be damaged.
+//!
+//! [`Health`] tracks current and max health, per-[`DamageType`] resistances and invulnerability
+//! windows, and drives a health-bar rectangle. Scripts embed it and implement [`Damageable`],
+//! whose [`Damageable::update_health`] applies the [`DamageDealt`] events aimed at them and calls
+//! [`Damageable::on_death`] and [`Damageable::on_revive`] on the way down and up.
+
+use crate::{
+    context::TickContext,
+    event::{DamageDealt, EventReader},
+};
+use fyrox::{
+    core::{algebra::Vector3, pool::Handle, reflect::prelude::*, visitor::prelude::*},
+    scene::{dim2::rectangle::Rectangle, graph::Graph, node::Node},
+};
+use serde::Deserialize;
+
+/// What a hit is made of, for resistances to apply to.
+#[derive(Visit, Reflect, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DamageType {
+    /// Swings and bot contact.
+    #[default]
+    Physical,
+    Explosion,
+    Fire,
+}
+
+/// Scales the damage of one type: 0.5 halves it, 0.0 makes the owner immune.
+#[derive(Visit, Reflect, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Resistance {
+    pub damage_type: DamageType,
+    pub multiplier: f32,
+}
+
+/// Transition reported by [`Health`] when health reaches zero or comes back from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthChange {
+    Died,
+    Revived,
+}
+
+#[derive(Visit, Reflect, Debug, Clone)]
+pub struct Health {
+    current: f32,
+    max: f32,
+    pub resistances: Vec<Resistance>,
+    invulnerability_timer: f32,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    damage_events: EventReader<DamageDealt>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new(100.0)
+    }
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            resistances: Vec::new(),
+            invulnerability_timer: 0.0,
+            damage_events: Default::default(),
+        }
+    }
+
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    /// Current health as a fraction of max health.
+    pub fn ratio(&self) -> f32 {
+        if self.max > 0.0 {
+            self.current / self.max
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+
+    /// Changes max health and fully heals.
+    pub fn set_max(&mut self, max: f32) {
+        self.max = max;
+        self.current = max;
+    }
+
+    /// Sets health directly, ignoring resistances and invulnerability.
+    pub fn set(&mut self, value: f32) -> Option<HealthChange> {
+        let was_dead = self.is_dead();
+        self.current = value.clamp(0.0, self.max);
+        match (was_dead, self.is_dead()) {
+            (false, true) => Some(HealthChange::Died),
+            (true, false) => Some(HealthChange::Revived),
+            _ => None,
+        }
+    }
+
+    /// Damage multiplier for hits of `damage_type`.
+    pub fn multiplier(&self, damage_type: DamageType) -> f32 {
+        self.resistances
+            .iter()
+            .find(|r| r.damage_type == damage_type)
+            .map_or(1.0, |r| r.multiplier)
+    }
+
+    /// Takes a hit, scaled by the resistance to `damage_type`. Ignored while dead or
+    /// invulnerable. Returns [`HealthChange::Died`] if the hit was lethal.
+    pub fn damage(&mut self, amount: f32, damage_type: DamageType) -> Option<HealthChange> {
+        if self.is_dead() || self.is_invulnerable() {
+            return None;
+        }
+        self.set(self.current - amount * self.multiplier(damage_type))
+    }
+
+    /// Restores health, up to max health. Dead owners stay dead, see [`Health::revive`].
+    pub fn heal(&mut self, amount: f32) {
+        if !self.is_dead() {
+            self.set(self.current + amount);
+        }
+    }
+
+    /// Brings a dead owner back with full health.
+    pub fn revive(&mut self) -> Option<HealthChange> {
+        self.set(self.max)
+    }
+
+    /// Ignores damage for the next `seconds`, or longer if a longer window is already running.
+    pub fn make_invulnerable(&mut self, seconds: f32) {
+        self.invulnerability_timer = self.invulnerability_timer.max(seconds);
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerability_timer > 0.0
+    }
+
+    /// Scales and shifts the `bar` rectangle to show the current health. The bar shrinks towards
+    /// its left edge.
+    pub fn update_bar(&self, graph: &mut Graph, bar: Handle<Node>) {
+        const FULL_WIDTH: f32 = 100.0;
+        let ratio = self.ratio();
+        if let Some(bar) = graph
+            .try_get_mut(bar)
+            .and_then(|n| n.cast_mut::<Rectangle>())
+        {
+            let transform = bar.local_transform_mut();
+            let scale = **transform.scale();
+            transform.set_scale(Vector3::new(ratio, scale.y, scale.z));
+            let position = **transform.position();
+            transform.set_position(Vector3::new(
+                (FULL_WIDTH - ratio * FULL_WIDTH) / 200.0,
+                position.y,
+                position.z,
+            ));
+        }
+    }
+}
+
+/// A script owning [`Health`].
+pub trait Damageable {
+    fn health(&self) -> &Health;
+
+    fn health_mut(&mut self) -> &mut Health;
+
+    /// Called for every hit taken, before [`Damageable::on_death`] if it was lethal.
+    fn on_damaged(&mut self, _hit: &DamageDealt, _ctx: &mut TickContext) {}
+
+    /// Called once when health reaches zero.
+    fn on_death(&mut self, _ctx: &mut TickContext) {}
+
+    /// Called once when a dead owner comes back.
+    fn on_revive(&mut self, _ctx: &mut TickContext) {}
+
+    /// Applies the hits aimed at the script's node since the last update and counts down
+    /// invulnerability. Call once per tick.
+    fn update_health(&mut self, ctx: &mut TickContext) {
+        let health = self.health_mut();
+        health.invulnerability_timer = (health.invulnerability_timer - ctx.dt).max(0.0);
+        let hits: Vec<DamageDealt> = ctx
+            .game
+            .events
+            .read(&mut health.damage_events)
+            .filter(|hit| hit.target == ctx.handle)
+            .cloned()
+            .collect();
+        for hit in hits {
+            let health = self.health_mut();
+            if health.is_dead() || health.is_invulnerable() {
+                continue;
+            }
+            let change = health.damage(hit.amount, hit.kind);
+            self.on_damaged(&hit, ctx);
+            if let Some(change) = change {
+                self.apply_health_change(change, ctx);
+            }
+        }
+    }
+
+    /// Calls the callback matching `change`.
+    fn apply_health_change(&mut self, change: HealthChange, ctx: &mut TickContext) {
+        match change {
+            HealthChange::Died => self.on_death(ctx),
+            HealthChange::Revived => self.on_revive(ctx),
+        }
+    }
+
+    /// Brings a dead owner back with full health.
+    fn revive(&mut self, ctx: &mut TickContext) {
+        if let Some(change) = self.health_mut().revive() {
+            self.apply_health_change(change, ctx);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! On-screen HUD: score, kills, level and wave, player health, item timers and the health of
+//! the boss.
+//!
+//! [`HudState`] gathers what the HUD shows from the game and the scene, and [`Hud`] owns the
+//! `fyrox-ui` widgets showing it. The game plugin refreshes the HUD after every update.
+
+use crate::{bot::Bot, health::Damageable, item::SpawnRule, Game, Player};
+use fyrox::{graph::BaseSceneGraph, scene::Scene};
+use fyrox_ui::{
+    border::BorderBuilder,
+    brush::Brush,
+    core::{color::Color, pool::Handle},
+    message::MessageDirection,
+    stack_panel::StackPanelBuilder,
+    text::{TextBuilder, TextMessage},
+    widget::{WidgetBuilder, WidgetMessage},
+    BuildContext, HorizontalAlignment, Thickness, UiNode, UserInterface, VerticalAlignment,
+};
+
+/// Width of the health bar at full health, in pixels.
+const HEALTH_BAR_WIDTH: f32 = 200.0;
+const HEALTH_BAR_HEIGHT: f32 = 14.0;
+/// Width of the boss health bar at full health, in pixels.
+const BOSS_BAR_WIDTH: f32 = 400.0;
+
+/// Health bar of the boss, shown while it is out.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BossBar {
+    pub name: String,
+    /// Name of the current phase.
+    pub phase: String,
+    pub health: f32,
+    pub max_health: f32,
+}
+
+impl BossBar {
+    fn collect(game: &Game, scene: &Scene) -> Option<Self> {
+        let node = scene.graph.try_get(game.waves.boss)?;
+        let bot = node.try_get_script::<Bot>()?;
+        let archetype = bot.archetype();
+        let phase = bot
+            .boss()
+            .and_then(|b| archetype.phases.get(b.phase() as usize))
+            .map(|p| p.name.clone())
+            .unwrap_or_default();
+        Some(Self {
+            name: archetype.name.clone(),
+            phase,
+            health: bot.health().current(),
+            max_health: bot.health().max(),
+        })
+    }
+
+    pub fn text(&self) -> String {
+        if self.phase.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} - {}", self.name, self.phase)
+        }
+    }
+
+    /// Width of the filled part of the bar.
+    pub fn width(&self) -> f32 {
+        bar_width(BOSS_BAR_WIDTH, self.health, self.max_health)
+    }
+}
+
+fn bar_width(width: f32, health: f32, max_health: f32) -> f32 {
+    if max_health > 0.0 {
+        width * (health / max_health).clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Snapshot of everything the HUD shows.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HudState {
+    pub score: f32,
+    pub kills: u32,
+    /// Name of the level, empty in a run without levels.
+    pub level: String,
+    pub wave: u32,
+    pub health: f32,
+    pub max_health: f32,
+    /// Item effects still running, with the seconds they have left.
+    pub effects: Vec<(String, f32)>,
+    /// Items spawning periodically, with the seconds until the next one appears.
+    pub next_spawns: Vec<(String, f32)>,
+    pub boss: Option<BossBar>,
+}
+
+impl HudState {
+    pub fn collect(game: &Game, scene: &Scene) -> Self {
+        let mut state = Self {
+            score: game.total_score,
+            kills: game.total_kills,
+            level: game
+                .current_level()
+                .map(|level| level.name.clone())
+                .unwrap_or_default(),
+            wave: game.waves.wave,
+            boss: BossBar::collect(game, scene),
+            ..Default::default()
+        };
+        let Some(player) = scene
+            .graph
+            .try_get(game.player)
+            .and_then(|n| n.try_get_script::<Player>())
+        else {
+            return state;
+        };
+
+        state.health = player.health.current();
+        state.max_health = player.health.max();
+        state.effects = player
+            .active_effects()
+            .iter()
+            .map(|e| (e.item.clone(), e.time_left.max(0.0)))
+            .collect();
+        state.next_spawns = game
+            .items
+            .iter()
+            .filter_map(|definition| match definition.spawn {
+                SpawnRule::Periodic { interval, .. } => {
+                    let elapsed = player.spawn_timer(&definition.name);
+                    Some((definition.name.clone(), (interval - elapsed).max(0.0)))
+                }
+                SpawnRule::LowHealth { .. } => None,
+            })
+            .collect();
+        state
+    }
+
+    pub fn score_text(&self) -> String {
+        format!("Score: {}", self.score)
+    }
+
+    pub fn kills_text(&self) -> String {
+        format!("Kills: {}", self.kills)
+    }
+
+    pub fn wave_text(&self) -> String {
+        if self.level.is_empty() {
+            format!("Wave: {}", self.wave)
+        } else {
+            format!("{} - Wave: {}", self.level, self.wave)
+        }
+    }
+
+    pub fn health_text(&self) -> String {
+        format!("Health: {:.0} / {:.0}", self.health, self.max_health)
+    }
+
+    /// One line per running effect, then one line per upcoming spawn.
+    pub fn timers_text(&self) -> String {
+        let effects = self
+            .effects
+            .iter()
+            .map(|(item, left)| format!("{item} active: {left:.1}s"));
+        let spawns = self
+            .next_spawns
+            .iter()
+            .map(|(item, left)| format!("Next {item} in {left:.0}s"));
+        effects.chain(spawns).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Width of the filled part of the health bar.
+    pub fn health_bar_width(&self) -> f32 {
+        bar_width(HEALTH_BAR_WIDTH, self.health, self.max_health)
+    }
+}
+
+/// Handles of the HUD widgets.
+#[derive(Debug, Clone)]
+pub struct Hud {
+    pub root: Handle<UiNode>,
+    pub score: Handle<UiNode>,
+    pub kills: Handle<UiNode>,
+    pub wave: Handle<UiNode>,
+    pub health: Handle<UiNode>,
+    pub health_fill: Handle<UiNode>,
+    pub timers: Handle<UiNode>,
+    /// Boss name and health bar at the top of the screen, hidden without a boss.
+    pub boss_panel: Handle<UiNode>,
+    pub boss_name: Handle<UiNode>,
+    pub boss_fill: Handle<UiNode>,
+    /// Last state shown, so unchanged widgets get no messages.
+    shown: Option<HudState>,
+}
+
+fn label(ctx: &mut BuildContext) -> Handle<UiNode> {
+    TextBuilder::new(
+        WidgetBuilder::new()
+            .with_margin(Thickness::uniform(2.0))
+            .with_foreground(Brush::Solid(Color::WHITE).into()),
+    )
+    .with_shadow(true)
+    .build(ctx)
+}
+
+/// Health bar of `width` pixels filled with `color`. Returns the bar and its filled part.
+fn bar(ctx: &mut BuildContext, width: f32, color: Color) -> (Handle<UiNode>, Handle<UiNode>) {
+    let fill = BorderBuilder::new(
+        WidgetBuilder::new()
+            .with_width(width)
+            .with_horizontal_alignment(HorizontalAlignment::Left)
+            .with_background(Brush::Solid(color).into()),
+    )
+    .build(ctx);
+    let bar = BorderBuilder::new(
+        WidgetBuilder::new()
+            .with_width(width)
+            .with_height(HEALTH_BAR_HEIGHT)
+            .with_margin(Thickness::uniform(2.0))
+            .with_horizontal_alignment(HorizontalAlignment::Left)
+            .with_background(Brush::Solid(Color::opaque(40, 40, 40)).into())
+            .with_child(fill),
+    )
+    .build(ctx);
+    (bar, fill)
+}
+
+impl Hud {
+    /// Builds the HUD in the top left corner of `ui`.
+    pub fn new(ui: &mut UserInterface) -> Self {
+        let ctx = &mut ui.build_ctx();
+        let score = label(ctx);
+        let kills = label(ctx);
+        let wave = label(ctx);
+        let health = label(ctx);
+        let (health_bar, health_fill) = bar(ctx, HEALTH_BAR_WIDTH, Color::opaque(200, 30, 30));
+        let timers = label(ctx);
+        let root = StackPanelBuilder::new(
+            WidgetBuilder::new()
+                .with_margin(Thickness::uniform(10.0))
+                .with_horizontal_alignment(HorizontalAlignment::Left)
+                .with_vertical_alignment(VerticalAlignment::Top)
+                .with_children([score, kills, wave, health, health_bar, timers]),
+        )
+        .build(ctx);
+
+        let boss_name = label(ctx);
+        let (boss_bar, boss_fill) = bar(ctx, BOSS_BAR_WIDTH, Color::opaque(150, 40, 200));
+        let boss_panel = StackPanelBuilder::new(
+            WidgetBuilder::new()
+                .with_visibility(false)
+                .with_margin(Thickness::uniform(10.0))
+                .with_horizontal_alignment(HorizontalAlignment::Center)
+                .with_vertical_alignment(VerticalAlignment::Top)
+                .with_children([boss_name, boss_bar]),
+        )
+        .build(ctx);
+
+        Self {
+            root,
+            score,
+            kills,
+            wave,
+            health,
+            health_fill,
+            timers,
+            boss_panel,
+            boss_name,
+            boss_fill,
+            shown: None,
+        }
+    }
+
+    /// Shows `state`, updating only the widgets whose content changed.
+    pub fn update(&mut self, ui: &UserInterface, state: HudState) {
+        let first = self.shown.is_none();
+        let shown = self.shown.take().unwrap_or_default();
+        let texts = [
+            (self.score, shown.score_text(), state.score_text()),
+            (self.kills, shown.kills_text(), state.kills_text()),
+            (self.wave, shown.wave_text(), state.wave_text()),
+            (self.health, shown.health_text(), state.health_text()),
+            (self.timers, shown.timers_text(), state.timers_text()),
+        ];
+        for (widget, old, new) in texts {
+            if first || old != new {
+                ui.send_message(TextMessage::text(widget, MessageDirection::ToWidget, new));
+            }
+        }
+        let width = state.health_bar_width();
+        if first || width != shown.health_bar_width() {
+            ui.send_message(WidgetMessage::width(
+                self.health_fill,
+                MessageDirection::ToWidget,
+                width,
+            ));
+        }
+        if first || state.boss != shown.boss {
+            self.show_boss(ui, state.boss.as_ref());
+        }
+        self.shown = Some(state);
+    }
+
+    fn show_boss(&self, ui: &UserInterface, boss: Option<&BossBar>) {
+        ui.send_message(WidgetMessage::visibility(
+            self.boss_panel,
+            MessageDirection::ToWidget,
+            boss.is_some(),
+        ));
+        if let Some(boss) = boss {
+            ui.send_message(TextMessage::text(
+                self.boss_name,
+                MessageDirection::ToWidget,
+                boss.text(),
+            ));
+            ui.send_message(WidgetMessage::width(
+                self.boss_fill,
+                MessageDirection::ToWidget,
+                boss.width(),
+            ));
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Input actions and their bindings.
+//!
+//! Scripts never look at key codes or gamepad buttons. The [`Game`](crate::Game) plugin turns
+//! keyboard and gamepad events into [`Action`] presses and releases using the
+//! [`InputBindings`] loaded from `input.ron`, which lives next to `settings.ron`, and hands them
+//! to the scripts through its [`InputStream`](crate::replay::InputStream).
+
+use crate::config::{self, ConfigError};
+use fyrox::{core::log::Log, keyboard::KeyCode};
+use gilrs::{Axis, Button, EventType, Gilrs};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+/// Location of the input bindings, relative to the working directory of the game.
+pub const INPUT_PATH: &str = "input.ron";
+
+/// Something the player can do, independent of the device used to do it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Attack,
+    /// Slides in the direction of movement, briefly ignoring damage.
+    Dodge,
+    /// Pauses or resumes the run, and starts it from the main menu.
+    Pause,
+    /// Starts a new run from the pause menu or the game-over screen.
+    Restart,
+    /// Exits the game from the menus.
+    Quit,
+    /// Submits the name typed for a new high score.
+    Confirm,
+    /// Saves the run to the quick-save slot.
+    QuickSave,
+    /// Loads the run of the quick-save slot.
+    QuickLoad,
+    /// Deals damage to the player, used to test the health bar.
+    DebugDamage,
+}
+
+/// Binds one direction of a gamepad axis to an action, e.g. left stick pushed left to
+/// [`Action::MoveLeft`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AxisBinding {
+    pub axis: Axis,
+    /// `true` if the action is triggered by positive axis values.
+    pub positive: bool,
+    pub action: Action,
+}
+
+fn default_dead_zone() -> f32 {
+    0.5
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InputBindings {
+    #[serde(default)]
+    pub keys: HashMap<Action, Vec<KeyCode>>,
+    #[serde(default)]
+    pub buttons: HashMap<Action, Vec<Button>>,
+    #[serde(default)]
+    pub axes: Vec<AxisBinding>,
+    /// Axis values closer to zero than this are ignored.
+    #[serde(default = "default_dead_zone")]
+    pub dead_zone: f32,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        use Action::*;
+        let keys = HashMap::from([
+            (MoveLeft, vec![KeyCode::KeyA, KeyCode::ArrowLeft]),
+            (MoveRight, vec![KeyCode::KeyD, KeyCode::ArrowRight]),
+            (MoveUp, vec![KeyCode::KeyW, KeyCode::ArrowUp]),
+            (MoveDown, vec![KeyCode::KeyS, KeyCode::ArrowDown]),
+            (Attack, vec![KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+            (Dodge, vec![KeyCode::ControlLeft, KeyCode::ControlRight]),
+            (Pause, vec![KeyCode::Escape, KeyCode::KeyP]),
+            (Restart, vec![KeyCode::KeyR]),
+            (Quit, vec![KeyCode::KeyQ]),
+            (Confirm, vec![KeyCode::Enter, KeyCode::NumpadEnter]),
+            (QuickSave, vec![KeyCode::F5]),
+            (QuickLoad, vec![KeyCode::F9]),
+            (DebugDamage, vec![KeyCode::Space]),
+        ]);
+        let buttons = HashMap::from([
+            (MoveLeft, vec![Button::DPadLeft]),
+            (MoveRight, vec![Button::DPadRight]),
+            (MoveUp, vec![Button::DPadUp]),
+            (MoveDown, vec![Button::DPadDown]),
+            (Attack, vec![Button::West, Button::RightTrigger]),
+            (Dodge, vec![Button::South, Button::LeftTrigger]),
+            (Pause, vec![Button::Start]),
+            (Restart, vec![Button::North]),
+            (Quit, vec![Button::Select]),
+        ]);
+        let axes = vec![
+            AxisBinding {
+                axis: Axis::LeftStickX,
+                positive: false,
+                action: MoveLeft,
+            },
+            AxisBinding {
+                axis: Axis::LeftStickX,
+                positive: true,
+                action: MoveRight,
+            },
+            AxisBinding {
+                axis: Axis::LeftStickY,
+                positive: true,
+                action: MoveUp,
+            },
+            AxisBinding {
+                axis: Axis::LeftStickY,
+                positive: false,
+                action: MoveDown,
+            },
+        ];
+        Self {
+            keys,
+            buttons,
+            axes,
+            dead_zone: default_dead_zone(),
+        }
+    }
+}
+
+impl InputBindings {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        config::load(path)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ConfigError> {
+        config::parse(source)
+    }
+
+    /// Actions bound to a key.
+    pub fn key_actions(&self, key: KeyCode) -> impl Iterator<Item = Action> + '_ {
+        self.keys
+            .iter()
+            .filter(move |(_, keys)| keys.contains(&key))
+            .map(|(action, _)| *action)
+    }
+
+    /// Actions bound to a gamepad button.
+    pub fn button_actions(&self, button: Button) -> impl Iterator<Item = Action> + '_ {
+        self.buttons
+            .iter()
+            .filter(move |(_, buttons)| buttons.contains(&button))
+            .map(|(action, _)| *action)
+    }
+}
+
+/// Connected gamepads, translated into action presses and releases.
+#[derive(Debug, Default)]
+pub struct Gamepads {
+    gilrs: Option<Gilrs>,
+    /// Axis bindings currently pushed past the dead zone, by index in [`InputBindings::axes`].
+    held_axes: Vec<bool>,
+}
+
+impl Gamepads {
+    /// Starts listening to gamepads. The game stays playable with the keyboard if the platform
+    /// has no gamepad support.
+    pub fn init() -> Self {
+        let gilrs = Gilrs::new()
+            .inspect_err(|err| Log::warn(format!("Gamepads are unavailable: {err}")))
+            .ok();
+        Self {
+            gilrs,
+            held_axes: Vec::new(),
+        }
+    }
+
+    /// Drains pending gamepad events into action presses and releases.
+    pub fn poll(&mut self, bindings: &InputBindings) -> Vec<(Action, bool)> {
+        let mut actions = Vec::new();
+        let Some(gilrs) = self.gilrs.as_mut() else {
+            return actions;
+        };
+        self.held_axes.resize(bindings.axes.len(), false);
+
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::ButtonPressed(button, _) => {
+                    actions.extend(bindings.button_actions(button).map(|a| (a, true)))
+                }
+                EventType::ButtonReleased(button, _) => {
+                    actions.extend(bindings.button_actions(button).map(|a| (a, false)))
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    for (binding, held) in bindings.axes.iter().zip(&mut self.held_axes) {
+                        if binding.axis != axis {
+                            continue;
+                        }
+                        let signed = if binding.positive { value } else { -value };
+                        let pushed = signed > bindings.dead_zone;
+                        if pushed != *held {
+                            *held = pushed;
+                            actions.push((binding.action, pushed));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        actions
+    }
+}
@@ -0,0 +1,429 @@
+//! Data-driven pickups.
+//!
+//! Every pickup (heart, bomb, fire, ...) is described by an [`ItemDefinition`] loaded from
+//! `items.ron`, which lives next to `settings.ron`. The [`Game`](crate::Game) plugin owns the
+//! [`ItemRegistry`], the player script spawns pickups according to their [`SpawnRule`] and
+//! applies their [`ItemEffect`] when collected.
+
+use crate::{
+    bot::Bot,
+    config::{self, ConfigError},
+    context::TickContext,
+    event::{DamageDealt, EventBus},
+    health::DamageType,
+    projectile::ProjectileSpec,
+    Player,
+};
+use fyrox::{
+    core::{
+        algebra::{Vector2, Vector3},
+        pool::Handle,
+        reflect::prelude::*,
+        visitor::prelude::*,
+    },
+    graph::SceneGraph,
+    gui::texture::Texture,
+    scene::{
+        base::BaseBuilder,
+        dim2::rectangle::{Rectangle, RectangleBuilder},
+        graph::Graph,
+        node::Node,
+        transform::TransformBuilder,
+    },
+};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Location of the item definitions, relative to the working directory of the game.
+pub const ITEMS_PATH: &str = "items.ron";
+
+fn default_pickup_radius() -> f32 {
+    1.0
+}
+
+/// Describes a single kind of pickup.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ItemDefinition {
+    /// Unique name of the item, also used as the name of spawned nodes.
+    pub name: String,
+    pub texture: String,
+    pub scale: f32,
+    #[serde(default)]
+    pub pulse: Pulse,
+    /// The player collects the item when closer than this distance.
+    #[serde(default = "default_pickup_radius")]
+    pub pickup_radius: f32,
+    /// Uncollected pickups disappear after this many seconds. `None` keeps them forever.
+    #[serde(default)]
+    pub lifetime: Option<f32>,
+    pub spawn: SpawnRule,
+    pub effect: ItemEffect,
+}
+
+impl ItemDefinition {
+    /// Checks the values the game would loop or panic on: spawn intervals must be positive,
+    /// durations and distances not negative, the distance range not reversed and the falloff
+    /// of an explosion sorted by distance.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |what: &str| ConfigError::Invalid(format!("item {}: {what}", self.name));
+        // NaN counts as out of range.
+        let negative = |value: f32| value.is_nan() || value < 0.0;
+        let not_positive = |value: f32| value.is_nan() || value <= 0.0;
+        let (min_distance, max_distance) = self.spawn.distance_range();
+        if negative(min_distance) || negative(max_distance - min_distance) {
+            return Err(invalid("min_distance must be between 0 and max_distance"));
+        }
+        if let SpawnRule::Periodic { interval, .. } = self.spawn {
+            if not_positive(interval) {
+                return Err(invalid("the spawn interval must be greater than 0"));
+            }
+        }
+        if self.lifetime.is_some_and(negative) {
+            return Err(invalid("lifetime must not be negative"));
+        }
+        if negative(self.effect.duration()) {
+            return Err(invalid("the effect duration must not be negative"));
+        }
+        match self.effect {
+            ItemEffect::DamageOverTime { interval, .. } if not_positive(interval) => {
+                Err(invalid("the damage interval must be greater than 0"))
+            }
+            ItemEffect::RangedWeapon { cooldown, .. } if negative(cooldown) => {
+                Err(invalid("cooldown must not be negative"))
+            }
+            ItemEffect::Explosion { ref falloff, .. }
+                if falloff.iter().any(|&(distance, _)| negative(distance))
+                    || falloff.windows(2).any(|pair| pair[1].0 < pair[0].0) =>
+            {
+                Err(invalid("falloff distances must be sorted and not negative"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Pulsing animation of a pickup lying on the map.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Pulse {
+    pub amplitude: f32,
+    pub frequency: f32,
+}
+
+/// Decides when and where a pickup appears.
+#[derive(Deserialize, Debug, Clone)]
+pub enum SpawnRule {
+    /// A single pickup is kept on the map while player health is below `threshold`.
+    LowHealth {
+        threshold: f32,
+        min_distance: f32,
+        max_distance: f32,
+    },
+    /// A new pickup appears every `interval` seconds.
+    Periodic {
+        interval: f32,
+        min_distance: f32,
+        max_distance: f32,
+    },
+}
+
+impl SpawnRule {
+    /// Allowed distance range from the player for a new pickup.
+    pub fn distance_range(&self) -> (f32, f32) {
+        match *self {
+            SpawnRule::LowHealth {
+                min_distance,
+                max_distance,
+                ..
+            }
+            | SpawnRule::Periodic {
+                min_distance,
+                max_distance,
+                ..
+            } => (min_distance, max_distance),
+        }
+    }
+}
+
+/// What happens when the player collects a pickup.
+#[derive(Deserialize, Debug, Clone)]
+pub enum ItemEffect {
+    /// Restores player health.
+    Heal { amount: f32 },
+    /// Turns the pickup into an explosion damaging bots around it. `falloff` is a list of
+    /// `(max_distance, damage)` pairs sorted by distance.
+    Explosion {
+        texture: String,
+        scale: f32,
+        duration: f32,
+        falloff: Vec<(f32, f32)>,
+    },
+    /// Damages every bot on the map each `interval` seconds for `duration` seconds.
+    DamageOverTime {
+        duration: f32,
+        interval: f32,
+        damage: f32,
+    },
+    /// Multiplies player movement speed.
+    SpeedBoost { multiplier: f32, duration: f32 },
+    /// Player ignores all damage: bot hits, projectiles and explosions alike.
+    Shield { duration: f32 },
+    /// Bots stop moving and attacking.
+    Freeze { duration: f32 },
+    /// The attack fires a `projectile` at most every `cooldown` seconds instead of swinging.
+    RangedWeapon {
+        duration: f32,
+        damage: f32,
+        cooldown: f32,
+        projectile: ProjectileSpec,
+    },
+}
+
+impl ItemEffect {
+    /// How long the effect lasts, zero for instant effects.
+    pub fn duration(&self) -> f32 {
+        match *self {
+            ItemEffect::Heal { .. } => 0.0,
+            ItemEffect::Explosion { duration, .. }
+            | ItemEffect::DamageOverTime { duration, .. }
+            | ItemEffect::SpeedBoost { duration, .. }
+            | ItemEffect::Shield { duration }
+            | ItemEffect::Freeze { duration }
+            | ItemEffect::RangedWeapon { duration, .. } => duration,
+        }
+    }
+}
+
+/// Behaviour of a collected item.
+pub trait Item {
+    /// Applies the instant part of the effect. Returns the time the effect stays active, or
+    /// `None` if there is nothing left to do after pickup.
+    fn on_collected(
+        &self,
+        pickup: Handle<Node>,
+        player: &mut Player,
+        ctx: &mut TickContext,
+    ) -> Option<f32>;
+
+    /// Called every frame while the effect is active.
+    fn on_active(&self, _effect: &mut ActiveEffect, _player: &mut Player, _ctx: &mut TickContext) {}
+
+    /// Whether the pickup node stays in the scene while the effect is active.
+    fn keeps_pickup(&self) -> bool {
+        false
+    }
+}
+
+impl Item for ItemEffect {
+    fn on_collected(
+        &self,
+        pickup: Handle<Node>,
+        player: &mut Player,
+        ctx: &mut TickContext,
+    ) -> Option<f32> {
+        match self {
+            ItemEffect::Heal { amount } => {
+                player.health.heal(*amount);
+                println!("Health: {}", player.health.current());
+                None
+            }
+            ItemEffect::Explosion {
+                texture,
+                scale,
+                duration,
+                falloff,
+            } => {
+                let explosion_texture = ctx.resource_manager.request::<Texture>(texture);
+                if let Some(rect) = ctx
+                    .scene
+                    .graph
+                    .try_get_mut(pickup)
+                    .and_then(|n| n.cast_mut::<Rectangle>())
+                {
+                    rect.material()
+                        .data_ref()
+                        .bind("diffuseTexture", explosion_texture);
+                    rect.local_transform_mut()
+                        .set_scale(Vector3::new(*scale, *scale, 1.0));
+                }
+
+                let center = ctx.scene.graph[pickup].global_position().xy();
+                let events = &mut ctx.game.events;
+                damage_bots(&ctx.scene.graph, events, DamageType::Explosion, |bot_pos| {
+                    let distance = (bot_pos - center).norm();
+                    falloff
+                        .iter()
+                        .find(|(max_distance, _)| distance <= *max_distance)
+                        .map(|(_, damage)| *damage)
+                });
+                Some(*duration)
+            }
+            ItemEffect::DamageOverTime { duration, .. } => Some(*duration),
+            ItemEffect::SpeedBoost {
+                multiplier,
+                duration,
+            } => {
+                player.speed_multiplier = *multiplier;
+                player.speed_boost_timer = *duration;
+                None
+            }
+            ItemEffect::Shield { duration } => {
+                player.health.make_invulnerable(*duration);
+                None
+            }
+            ItemEffect::Freeze { duration } => {
+                for (_, node) in ctx.scene.graph.pair_iter_mut() {
+                    if !node.visibility() {
+                        continue;
+                    }
+                    if let Some(bot) = node.script_mut(0).and_then(|s| s.cast_mut::<Bot>()) {
+                        bot.freeze(*duration);
+                    }
+                }
+                None
+            }
+            ItemEffect::RangedWeapon { duration, .. } => Some(*duration),
+        }
+    }
+
+    fn on_active(&self, effect: &mut ActiveEffect, _player: &mut Player, ctx: &mut TickContext) {
+        if let ItemEffect::DamageOverTime {
+            interval, damage, ..
+        } = self
+        {
+            effect.tick_accum += ctx.dt;
+            while effect.tick_accum >= *interval {
+                effect.tick_accum -= *interval;
+                println!("🔥 {} tick: {} damage to all bots", effect.item, damage);
+                let events = &mut ctx.game.events;
+                damage_bots(&ctx.scene.graph, events, DamageType::Fire, |_| {
+                    Some(*damage)
+                });
+            }
+        }
+    }
+
+    fn keeps_pickup(&self) -> bool {
+        matches!(self, ItemEffect::Explosion { .. })
+    }
+}
+
+/// Lands a hit on every visible bot. `damage` maps a bot position to the damage it takes.
+fn damage_bots(
+    graph: &Graph,
+    events: &mut EventBus,
+    kind: DamageType,
+    damage: impl Fn(Vector2<f32>) -> Option<f32>,
+) {
+    for (target, node) in graph.pair_iter() {
+        if !node.visibility() || node.try_get_script::<Bot>().is_none() {
+            continue;
+        }
+        if let Some(amount) = damage(node.global_position().xy()) {
+            events.publish(DamageDealt {
+                target,
+                source: Handle::NONE,
+                amount,
+                kind,
+            });
+        }
+    }
+}
+
+/// A pickup lying on the map.
+#[derive(Visit, Reflect, Debug, Clone, Default)]
+pub struct Pickup {
+    pub item: String,
+    pub node: Handle<Node>,
+    pub age: f32,
+}
+
+/// An effect that outlives the moment of pickup.
+#[derive(Visit, Reflect, Debug, Clone, Default)]
+pub struct ActiveEffect {
+    pub item: String,
+    /// Pickup node kept alive for the duration of the effect, if any.
+    pub node: Handle<Node>,
+    pub time_left: f32,
+    pub tick_accum: f32,
+}
+
+/// All item definitions known to the game.
+#[derive(Debug, Default)]
+pub struct ItemRegistry {
+    definitions: Vec<ItemDefinition>,
+}
+
+impl ItemRegistry {
+    pub fn new(definitions: Vec<ItemDefinition>) -> Self {
+        Self { definitions }
+    }
+
+    /// Reads the item definitions at `path`. Fails on the first definition that doesn't
+    /// [validate](ItemDefinition::validate), or that reuses the name of another one.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        config::load(path).and_then(Self::validated)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ConfigError> {
+        config::parse(source).and_then(Self::validated)
+    }
+
+    fn validated(definitions: Vec<ItemDefinition>) -> Result<Self, ConfigError> {
+        for (index, definition) in definitions.iter().enumerate() {
+            definition.validate()?;
+            if definitions[..index]
+                .iter()
+                .any(|d| d.name == definition.name)
+            {
+                return Err(ConfigError::Invalid(format!(
+                    "item {} is defined twice",
+                    definition.name
+                )));
+            }
+        }
+        Ok(Self::new(definitions))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ItemDefinition> {
+        self.definitions.iter().find(|d| d.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ItemDefinition> {
+        self.definitions.iter()
+    }
+}
+
+/// Spawns a pickup node for `definition` at `pos`.
+pub fn spawn_item(
+    definition: &ItemDefinition,
+    pos: Vector2<f32>,
+    context: &mut TickContext,
+) -> Handle<Node> {
+    let texture = context
+        .resource_manager
+        .request::<Texture>(&definition.texture);
+    let scale = definition.scale;
+    let item = RectangleBuilder::new(
+        BaseBuilder::new()
+            .with_name(definition.name.as_str())
+            .with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(pos.x, pos.y, 0.0))
+                    .with_local_scale(Vector3::new(scale, scale, scale))
+                    .build(),
+            ),
+    )
+    .build(&mut context.scene.graph);
+
+    if let Some(rect) = context
+        .scene
+        .graph
+        .try_get_mut(item)
+        .and_then(|n| n.cast_mut::<Rectangle>())
+    {
+        rect.material().data_ref().bind("diffuseTexture", texture);
+    }
+
+    println!("{} spawned at: {:?}", definition.name, pos);
+    item
+}
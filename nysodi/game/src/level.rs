@@ -0,0 +1,177 @@
+//! Levels of a run.
+//!
+//! `levels.ron` lists the levels a run goes through, in order. Each [`LevelDefinition`] names
+//! the scene of the level, its waves and enemies, the items found on it and the [`Goal`]
+//! completing it. The [`Game`](crate::Game) plays the levels one after the other: once the goal
+//! of a level is met it shows the level-complete screen, then loads the scene of the next level
+//! and carries the score and statistics of the run over. Completing the last level wins the run.
+//! A level may generate its arena on top of its scene, see [`generator`](crate::generator).
+//! Without levels, the run is a single level on the scene of the game, won by defeating the boss.
+
+use crate::{
+    arena::Arena,
+    config::{self, ConfigError},
+    generator::GeneratorSettings,
+    item::ItemRegistry,
+    wave::{WaveConfig, WAVES_PATH},
+};
+use fyrox::core::{algebra::Vector2, log::Log, reflect::prelude::*, visitor::prelude::*};
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Location of the level list, relative to the working directory of the game.
+pub const LEVELS_PATH: &str = "levels.ron";
+
+fn default_waves() -> String {
+    WAVES_PATH.to_owned()
+}
+
+/// What completes a level.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Goal {
+    /// Defeat the boss of the waves of the level.
+    #[default]
+    DefeatBoss,
+    /// Clear this many waves.
+    ClearWaves(u32),
+    /// Defeat this many bots.
+    Kills(u32),
+    /// Stay alive for this many seconds.
+    Survive(f32),
+}
+
+impl Goal {
+    pub fn is_met(self, progress: &LevelProgress) -> bool {
+        match self {
+            Goal::DefeatBoss => progress.boss_defeated,
+            Goal::ClearWaves(waves) => progress.waves_cleared >= waves,
+            Goal::Kills(kills) => progress.kills >= kills,
+            Goal::Survive(seconds) => progress.time >= seconds,
+        }
+    }
+}
+
+/// What the player achieved on the current level, towards its [`Goal`].
+#[derive(Visit, Reflect, Debug, Clone, Default, PartialEq)]
+pub struct LevelProgress {
+    pub waves_cleared: u32,
+    pub kills: u32,
+    /// Seconds played on the level, pauses excluded.
+    pub time: f32,
+    pub boss_defeated: bool,
+}
+
+/// A level as described in `levels.ron`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct LevelDefinition {
+    pub name: String,
+    /// Path of the scene of the level.
+    pub scene: String,
+    /// Corners of the arena, `((min_x, min_y), (max_x, max_y))`. Read from the scene if absent.
+    #[serde(default)]
+    pub arena: Option<((f32, f32), (f32, f32))>,
+    /// Generates the arena of the level, replacing the tile maps and walls of its scene.
+    #[serde(default)]
+    pub generate: Option<GeneratorSettings>,
+    /// Path of the wave definitions of the level.
+    #[serde(default = "default_waves")]
+    pub waves: String,
+    /// Archetypes spawned by every wave of the level, with their relative weights. The waves
+    /// keep their own enemies if empty.
+    #[serde(default)]
+    pub enemies: Vec<(String, u32)>,
+    /// Names of the items of `items.ron` found on the level. Every item if empty.
+    #[serde(default)]
+    pub items: Vec<String>,
+    #[serde(default)]
+    pub goal: Goal,
+}
+
+/// A level ready to be played, with its waves and items.
+#[derive(Debug, Clone)]
+pub struct Level {
+    pub name: String,
+    pub scene: PathBuf,
+    /// Arena set by the level, overriding the one of the scene.
+    pub arena: Option<Arena>,
+    pub generate: Option<GeneratorSettings>,
+    pub waves: Arc<WaveConfig>,
+    pub items: Arc<ItemRegistry>,
+    pub goal: Goal,
+}
+
+impl Level {
+    /// Builds the level of `definition` spawning `waves`, with its items picked out of `items`.
+    pub fn new(definition: LevelDefinition, mut waves: WaveConfig, items: &ItemRegistry) -> Self {
+        if !definition.enemies.is_empty() {
+            for wave in &mut waves.waves {
+                wave.enemies.clone_from(&definition.enemies);
+            }
+        }
+        for name in &definition.items {
+            if items.get(name).is_none() {
+                Log::err(format!("Unknown item {name} in level {}", definition.name));
+            }
+        }
+        let items = items
+            .iter()
+            .filter(|item| definition.items.is_empty() || definition.items.contains(&item.name))
+            .cloned()
+            .collect();
+        Self {
+            name: definition.name,
+            scene: definition.scene.into(),
+            arena: definition.arena.map(|((min_x, min_y), (max_x, max_y))| {
+                Arena::new(Vector2::new(min_x, min_y), Vector2::new(max_x, max_y))
+            }),
+            generate: definition.generate,
+            waves: Arc::new(waves),
+            items: Arc::new(ItemRegistry::new(items)),
+            goal: definition.goal,
+        }
+    }
+
+    /// Reads the waves of `definition` and builds its level.
+    pub fn load(definition: LevelDefinition, items: &ItemRegistry) -> Result<Self, ConfigError> {
+        let waves = config::load(&definition.waves)?;
+        Ok(Self::new(definition, waves, items))
+    }
+}
+
+/// The levels of a run, in order.
+#[derive(Debug, Clone, Default)]
+pub struct LevelList {
+    levels: Vec<Level>,
+}
+
+impl LevelList {
+    pub fn new(levels: Vec<Level>) -> Self {
+        Self { levels }
+    }
+
+    /// Reads the level list at `path` and the waves of every level, picking the items of the
+    /// levels out of `items`.
+    pub fn load(path: impl AsRef<Path>, items: &ItemRegistry) -> Result<Self, ConfigError> {
+        let definitions: Vec<LevelDefinition> = config::load(path)?;
+        definitions
+            .into_iter()
+            .map(|definition| Level::load(definition, items))
+            .collect::<Result<_, _>>()
+            .map(Self::new)
+    }
+
+    pub fn get(&self, index: u32) -> Option<&Level> {
+        self.levels.get(index as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+}
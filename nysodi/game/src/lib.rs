@@ -1,60 +1,110 @@
 //! Game project.
 
+pub mod ai;
+pub mod arena;
+pub mod boss;
 pub mod bot;
+pub mod combat;
+pub mod config;
+pub mod context;
+pub mod enemy;
+pub mod event;
+pub mod flock;
+pub mod generator;
+pub mod health;
+pub mod hud;
+pub mod input;
+pub mod item;
+pub mod level;
+pub mod menu;
+pub mod nav;
+pub mod projectile;
+pub mod replay;
+pub mod rng;
+pub mod save;
+pub mod scores;
+pub mod sim;
+pub mod wave;
         
 // ANCHOR: imports
+use crate::arena::Arena;
 use crate::bot::Bot;
+use crate::combat::{Combo, Dodge};
+use crate::context::{keyboard_input, TickContext};
+use crate::enemy::{ArchetypeRegistry, ENEMIES_PATH};
+use crate::event::{
+    DamageDealt, EntityDied, EntityKind, EventBus, EventReader, GameOver, ItemCollected,
+};
+use crate::flock::Flock;
+use crate::generator::Layout;
+use crate::health::{Damageable, Health};
+use crate::hud::{Hud, HudState};
+use crate::input::{Action, Gamepads, InputBindings, INPUT_PATH};
+use crate::item::{
+    ActiveEffect, Item, ItemDefinition, ItemEffect, ItemRegistry, Pickup, SpawnRule, ITEMS_PATH,
+};
+use crate::level::{Goal, Level, LevelList, LevelProgress, LEVELS_PATH};
+use crate::menu::{Menu, UiState};
+use crate::nav::NavGrid;
+use crate::projectile::{Faction, Projectile};
+use crate::replay::InputStream;
+use crate::rng::GameRng;
+use crate::scores::{HighScore, HighScoreTable, RunStats, DEFAULT_NAME, HIGH_SCORES_PATH};
+use crate::wave::{WaveConfig, WaveDirector, WaveEvent, WAVES_PATH};
 use fyrox::{
+    asset::manager::ResourceManager,
     core::{
-        algebra::{Vector2, Vector3},                                
+        algebra::{Vector2, Vector3},
+        log::Log,
         pool::Handle,
         reflect::prelude::*,
-        task::TaskPool,
         type_traits::prelude::*,
         visitor::prelude::*,
-        math::Rect,
-        color::Color,
     },
-    engine::ScriptProcessor,
-    event::{ElementState, Event, WindowEvent},
-    keyboard::{KeyCode, PhysicalKey},
+    event::Event,
+    graph::{BaseSceneGraph, SceneGraph},
+    gui::{
+        button::ButtonMessage,
+        message::{MessageDirection, UiMessage},
+        text::TextMessage,
+    },
     plugin::{Plugin, PluginContext, PluginRegistrationContext},
+    rand::Rng,
     scene::{
         animation::spritesheet::SpriteSheetAnimation,
-        base::BaseBuilder,
         dim2::{
-            collider::{Collider, ColliderBuilder, ColliderShape, CuboidShape},
-            rectangle::{Rectangle, RectangleBuilder},
-            rigidbody::{RigidBody, RigidBodyBuilder},
+            rectangle::Rectangle,
+            rigidbody::RigidBody,
         },
         graph::Graph,
         node::Node,
-        transform::TransformBuilder,
         Scene,
     },
-    script::{ScriptContext, ScriptTrait},
-    rand::{self, Rng},
-    material::{Material, MaterialResource, MaterialResourceExtension},
-    gui::{
-        texture::{Texture, TextureResource},
-        widget::WidgetBuilder,
-    },
-    asset::manager::ResourceManager,
+    script::{constructor::ScriptConstructorContainer, ScriptContext, ScriptTrait},
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
-use std::{path::Path, ptr::null_mut};
 // ANCHOR_END: imports
 
-const MIN_DISTANCE_FROM_PLAYER: f32 = 5.0;   // never spawn closer than 5 units
-const MAX_DISTANCE_FROM_PLAYER: f32 = 11.0;  // clamp max radius if you like
-const MIN_SEPARATION: f32 = 4.0;             // bomb & fire at least 4 units apart
+const MIN_SEPARATION: f32 = 4.0; // pickups at least 4 units apart
+const MAX_PLACEMENT_ATTEMPTS: usize = 16; // give up on separation after this many tries
+const MAX_NAME_LENGTH: usize = 12; // characters kept of a high-score name
+const DEFAULT_SCENE: &str = "data/scene.rgs"; // scene of the run without levels
 
+/// Random point of `arena` between `min_r` and `max_r` from `center`. When the arena has no
+/// room that far away, the farthest of the points tried.
 pub fn random_point_around(
     center: Vector2<f32>,
     min_r: f32,
     max_r: f32,
+    arena: &Arena,
     rng: &mut impl Rng
 ) -> Vector2<f32> {
-    loop {
+    let mut farthest = arena.clamp(center);
+    for _ in 0..MAX_PLACEMENT_ATTEMPTS {
         // pick random angle
         let theta = rng.gen_range(0.0..std::f32::consts::TAU);
         // radius uniformly between min and max
@@ -62,14 +112,16 @@ pub fn random_point_around(
         let candidate = Vector2::new(center.x + r * theta.cos(),
                                      center.y + r * theta.sin());
         // clamp to arena:
-        let clamped = Vector2::new(candidate.x.clamp(-11.0,11.0),
-                                   candidate.y.clamp(-4.0,17.0));
+        let clamped = arena.clamp(candidate);
         // ensure after clamping it's still at least min_r away?
         if (clamped - center).norm() >= min_r {
             return clamped;
         }
-        // otherwise retry
+        if (clamped - center).norm() > (farthest - center).norm() {
+            farthest = clamped;
+        }
     }
+    farthest
 }
 
 
@@ -80,21 +132,506 @@ pub struct Game {
     pub player: Handle<Node>,
     pub total_score: f32,
     pub bot_kill_count: u32, // Thanks to the Default flag, this will be initialized to 0, without needing to have impl Default for Game
-    #[visit(optional)] #[reflect(hidden)]
-    bot_spawn_timer: f32,
-    #[visit(optional)] #[reflect(hidden)]
-    bot_proto: Handle<Node>,
+    /// Bots defeated during the run. Unlike `bot_kill_count`, bot reactions don't reset it.
+    pub total_kills: u32,
+    /// Seconds the run has been played, pauses excluded.
+    pub run_time: f32,
+    pub items_collected: u32,
+    /// Set when the last level was completed, which ends the run with a victory.
+    pub victory: bool,
+    /// Index of the level being played in `levels`.
+    pub level: u32,
+    /// Progress towards the goal of the current level.
+    pub level_progress: LevelProgress,
+    /// Screen the game is on. The run only advances while playing.
+    pub state: UiState,
+    /// Set when the player chose to quit; the plugin then closes the game.
+    pub quit_requested: bool,
+    /// Set when the player chose to restart; the plugin then reloads the scene.
+    pub restart_requested: bool,
+    /// Set when the player moves on from a completed level; the plugin then loads the scene of
+    /// the next one.
+    pub next_level_requested: bool,
+    /// Scene of the run, reloaded on restart.
+    pub scene_path: PathBuf,
+    /// Directory of the save slots. Saving is disabled without one, e.g. in the headless
+    /// simulation.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub save_dir: Option<PathBuf>,
+    /// Save slot to load; the plugin replaces the scene with the saved one.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub load_requested: Option<PathBuf>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub high_scores: HighScoreTable,
+    /// File the high-score table is written to. The table stays in memory without one, e.g. in
+    /// the headless simulation.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub high_scores_path: Option<PathBuf>,
+    /// Rank of the run in the high-score table, once it ended and made the table.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub new_high_score: Option<usize>,
+    /// Name typed on the high-score entry screen.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub player_name: String,
+    pub waves: WaveDirector,
+    /// Walkable cells of the scene, rebuilt when the scene changes.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub nav: NavGrid,
+    /// Area of the scene things spawn in, read along with the walkable cells.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub arena: Arena,
+    /// Slots of the bots around the player, updated every tick.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub flock: Flock,
+    /// Levels of the run. The run is a single level on `scene_path` without any.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub levels: Arc<LevelList>,
+    /// Items of the current level.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub items: Arc<ItemRegistry>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub wave_config: Arc<WaveConfig>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub enemies: Arc<ArchetypeRegistry>,
+    /// Source of every random decision made during a run.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub rng: GameRng,
+    /// Input actions handed to the scripts, live, recorded or replayed.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub input: InputStream,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub bindings: Arc<InputBindings>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub gamepads: Gamepads,
+    /// Gameplay events published by the scripts and game-wide systems.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub events: EventBus,
+    /// Readers of the events the plugin itself reacts to.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub died_events: EventReader<EntityDied>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub wave_events: EventReader<WaveEvent>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub game_over_events: EventReader<GameOver>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub collected_events: EventReader<ItemCollected>,
+    /// On-screen HUD, only built when the game runs with a window.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub hud: Option<Hud>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub menu: Option<Menu>,
+}
+
+/// Registers the constructors of every script of the game.
+pub fn register_scripts(ctors: &ScriptConstructorContainer) {
+    ctors.add::<crate::Player>("Player");
+    ctors.add::<Bot>("Bot");
+    ctors.add::<Projectile>("Projectile");
+}
+
+impl Game {
+    /// Creates a game whose run is reproducible from `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: GameRng::new(seed),
+            ..Default::default()
+        }
+    }
+
+    /// Reads the designer-editable data files from the working directory.
+    pub fn load_config(&mut self) {
+        self.items = Arc::new(ItemRegistry::load(ITEMS_PATH).unwrap_or_else(|err| {
+            Log::err(format!("{ITEMS_PATH}: {err}"));
+            ItemRegistry::default()
+        }));
+        self.wave_config = Arc::new(config::load(WAVES_PATH).unwrap_or_else(|err| {
+            Log::err(format!("{WAVES_PATH}: {err}"));
+            WaveConfig::default()
+        }));
+        self.enemies = Arc::new(ArchetypeRegistry::load(ENEMIES_PATH).unwrap_or_else(|err| {
+            Log::err(format!("{ENEMIES_PATH}: {err}"));
+            ArchetypeRegistry::default()
+        }));
+        self.bindings = Arc::new(InputBindings::load(INPUT_PATH).unwrap_or_else(|err| {
+            Log::err(format!("{INPUT_PATH}: {err}"));
+            InputBindings::default()
+        }));
+        self.levels = Arc::new(
+            LevelList::load(LEVELS_PATH, &self.items).unwrap_or_else(|err| {
+                Log::err(format!("{LEVELS_PATH}: {err}"));
+                LevelList::default()
+            }),
+        );
+        self.apply_level();
+    }
+
+    /// The level being played, if the run has levels.
+    pub fn current_level(&self) -> Option<&Level> {
+        self.levels.get(self.level)
+    }
+
+    /// What completes the current level. A run without levels is won by defeating the boss.
+    pub fn goal(&self) -> Goal {
+        self.current_level()
+            .map_or(Goal::DefeatBoss, |level| level.goal)
+    }
+
+    /// Switches the scene, waves and items to those of the current level, if the run has
+    /// levels.
+    pub fn apply_level(&mut self) {
+        let Some(level) = self.current_level().cloned() else {
+            return;
+        };
+        self.scene_path = level.scene;
+        self.wave_config = level.waves;
+        self.items = level.items;
+    }
+
+    /// Moves on to the next level, keeping the score and statistics of the run. The scene of
+    /// the level is loaded by the caller, then handed to [`Game::prepare_scene`].
+    pub fn next_level(&mut self) {
+        self.level += 1;
+        self.level_progress = LevelProgress::default();
+        self.player = Handle::NONE;
+        self.next_level_requested = false;
+        self.state = UiState::Playing;
+        self.apply_level();
+        if let Some(level) = self.current_level() {
+            println!("▶ Level {}: {}", self.level + 1, level.name);
+        }
+    }
+
+    /// Ends the current level once its goal is met: moves to the level-complete screen, or
+    /// wins the run after the last level.
+    fn complete_level(&mut self) {
+        if (self.level as usize + 1) < self.levels.len() {
+            println!("▶ Level {} complete!", self.level + 1);
+            self.state = UiState::LevelComplete;
+        } else {
+            println!("▶ Victory!");
+            self.victory = true;
+            self.end_run();
+        }
+    }
+
+    /// Resets everything the game keeps across a run, so every run starts identically. The
+    /// scene itself is replaced by the caller, then handed to [`Game::prepare_scene`].
+    pub fn reset_run(&mut self) {
+        self.player = Handle::NONE;
+        self.total_score = 0.0;
+        self.bot_kill_count = 0;
+        self.total_kills = 0;
+        self.run_time = 0.0;
+        self.items_collected = 0;
+        self.victory = false;
+        self.level = 0;
+        self.level_progress = LevelProgress::default();
+        self.next_level_requested = false;
+        self.apply_level();
+        self.new_high_score = None;
+        self.rng = GameRng::new(self.rng.seed());
+        self.restart_requested = false;
+        self.state = UiState::Playing;
+        println!("Game Restarted!");
+    }
+
+    /// Prepares a freshly loaded scene for a new run.
+    pub fn prepare_scene(&mut self, scene: &mut Scene, resource_manager: &ResourceManager) {
+        // Hidden skeletons placed in the scene used to be revealed one by one; enemies are
+        // spawned by the wave director now.
+        let unused_bots: Vec<_> = scene
+            .graph
+            .pair_iter()
+            .filter(|(_, node)| !node.visibility() && node.try_get_script::<Bot>().is_some())
+            .map(|(handle, _)| handle)
+            .collect();
+        for bot in unused_bots {
+            if scene.graph.is_valid_handle(bot) {
+                scene.graph.remove_node(bot);
+            }
+        }
+
+        // Generated arenas are built from their own generator, so drawing one doesn't change the
+        // rest of the run.
+        if let Some(settings) = self
+            .current_level()
+            .and_then(|level| level.generate.clone())
+        {
+            let seed = settings
+                .seed
+                .unwrap_or_else(|| generator::level_seed(self.rng.seed(), self.level));
+            let mut rng = GameRng::new(seed);
+            let layout = Layout::generate(&settings, &mut rng);
+            layout.build(scene, &settings.tiles, resource_manager, &mut rng);
+        }
+
+        self.events.clear();
+        self.nav = NavGrid::default();
+        self.flock = Flock::default();
+        self.waves
+            .reset(&self.wave_config, &self.enemies, resource_manager);
+    }
+
+    /// Whether the run advances. Scripts, physics and game-wide timers are frozen in the menus.
+    pub fn is_running(&self) -> bool {
+        self.state == UiState::Playing
+    }
+
+    /// Moves between screens on a press of `action`.
+    fn apply_ui_action(&mut self, action: Action) {
+        if action == Action::Quit && self.state.can_quit() {
+            println!("Exiting game...");
+            self.quit_requested = true;
+        } else if action == Action::Restart && self.state.can_restart() {
+            self.restart_requested = true;
+        } else if action == Action::Confirm && self.state == UiState::LevelComplete {
+            self.next_level_requested = true;
+        } else if action == Action::QuickLoad && self.state.can_load() {
+            self.load_requested = self
+                .slot_path(save::QUICK_SLOT)
+                .filter(|path| path.exists());
+        } else if let Some(state) = self.state.next(action) {
+            if self.state == UiState::NameEntry {
+                self.submit_name();
+            }
+            self.state = state;
+        }
+    }
+
+    /// Statistics of the run so far.
+    pub fn run_stats(&self) -> RunStats {
+        RunStats {
+            score: self.total_score,
+            kills: self.total_kills,
+            survival_time: self.run_time,
+            items_collected: self.items_collected,
+        }
+    }
+
+    /// Ends the run: adds it to the high-score table if it makes it, and moves to the name
+    /// entry or the game-over screen.
+    fn end_run(&mut self) {
+        let entry = HighScore {
+            name: DEFAULT_NAME.to_owned(),
+            stats: self.run_stats(),
+            date: scores::today(),
+        };
+        self.new_high_score = self.high_scores.insert(entry);
+        if self.new_high_score.is_some() {
+            self.save_high_scores();
+            self.state = UiState::NameEntry;
+        } else {
+            self.state = UiState::GameOver;
+        }
+    }
+
+    /// Names the new high score after the name typed by the player.
+    fn submit_name(&mut self) {
+        let name = self.player_name.trim();
+        let name = if name.is_empty() { DEFAULT_NAME } else { name };
+        if let Some(entry) = self
+            .new_high_score
+            .and_then(|rank| self.high_scores.entries.get_mut(rank))
+        {
+            entry.name = name.chars().take(MAX_NAME_LENGTH).collect();
+            self.save_high_scores();
+        }
+    }
+
+    fn save_high_scores(&self) {
+        if let Some(path) = &self.high_scores_path {
+            if let Err(err) = self.high_scores.save(path) {
+                Log::err(format!("{}: {err}", path.display()));
+            }
+        }
+    }
+
+    /// File of the save slot `slot`, if saving is enabled.
+    pub fn slot_path(&self, slot: &str) -> Option<PathBuf> {
+        self.save_dir.as_ref().map(|dir| save::slot_path(dir, slot))
+    }
+
+    /// Saves the run to the slot `slot`, if saving is enabled.
+    pub fn save_slot(&mut self, slot: &str, scene: &mut Scene) {
+        let Some(path) = self.slot_path(slot) else {
+            return;
+        };
+        match save::save_run(&path, scene, self) {
+            Ok(()) => println!("▶ Game saved to {}", path.display()),
+            Err(err) => Log::err(format!("{}: {err}", path.display())),
+        }
+    }
+
+    /// Advances game-wide systems by `dt` seconds. Runs before the scripts of the frame.
+    pub fn update_scene(&mut self, scene: &mut Scene, resource_manager: &ResourceManager, dt: f32) {
+        if self.is_running() {
+            self.events.update();
+            if self
+                .events
+                .read(&mut self.game_over_events)
+                .next()
+                .is_some()
+            {
+                self.end_run();
+            }
+        }
+
+        // Input is consumed in every state, so recorded menu choices replay on the same frames.
+        let mut quick_save = false;
+        for (action, pressed) in self.input.next_frame(dt) {
+            if pressed {
+                self.apply_ui_action(action);
+                quick_save |= action == Action::QuickSave;
+            }
+            // Releases always reach the player, so keys let go of in a menu don't stick.
+            if self.is_running() || !pressed {
+                context::dispatch_action(scene, self, resource_manager, dt, action, pressed);
+            }
+        }
+
+        scene
+            .graph
+            .physics2d
+            .enabled
+            .set_value_and_mark_modified(self.is_running());
+        if !self.is_running() {
+            // A paused run doesn't update, so it can be saved right away.
+            if quick_save && self.state == UiState::Paused {
+                self.save_slot(save::QUICK_SLOT, scene);
+            }
+            return;
+        }
+
+        if !self.nav.is_ready() {
+            self.nav = NavGrid::build(&scene.graph);
+            self.arena = self
+                .current_level()
+                .and_then(|level| level.arena)
+                .unwrap_or_else(|| Arena::from_scene(&scene.graph));
+            self.waves.arena = self.arena;
+            self.waves.spawn_points = arena::spawn_points(&scene.graph);
+        }
+        self.nav.new_tick();
+        self.flock.update(&scene.graph, self.player);
+
+        self.run_time += dt;
+        self.level_progress.time += dt;
+        self.update_score();
+        self.items_collected += self.events.read(&mut self.collected_events).count() as u32;
+
+        self.waves.update(
+            &self.wave_config,
+            scene,
+            self.player,
+            dt,
+            &mut self.rng,
+            &mut self.events,
+        );
+
+        let mut cleared = false;
+        for event in self.events.read(&mut self.wave_events) {
+            match event {
+                WaveEvent::Started { wave, enemies } => {
+                    println!("▶ Wave {} started: {} enemies incoming", wave, enemies)
+                }
+                WaveEvent::Cleared { wave } => {
+                    println!("▶ Wave {} cleared!", wave);
+                    self.level_progress.waves_cleared = *wave;
+                    cleared = true;
+                }
+                WaveEvent::BossStarted { .. } => println!("▶ The boss has arrived!"),
+                WaveEvent::BossDefeated => {
+                    println!("▶ The boss was defeated!");
+                    self.level_progress.boss_defeated = true;
+                }
+            }
+        }
+        if self.state == UiState::Playing && self.goal().is_met(&self.level_progress) {
+            self.complete_level();
+        }
+        // Saves are taken at the end of the update, where a loaded run picks up again.
+        if cleared {
+            self.save_slot(save::AUTO_SLOT, scene);
+        }
+        if quick_save {
+            self.save_slot(save::QUICK_SLOT, scene);
+        }
+    }
+
+    /// Awards points for the bots defeated since the last update.
+    fn update_score(&mut self) {
+        for died in self.events.read(&mut self.died_events) {
+            if died.kind == EntityKind::Bot {
+                self.bot_kill_count += 1;
+                self.total_kills += 1;
+                self.level_progress.kills += 1;
+                self.total_score += 10.0;
+                println!(
+                    "▶ Bot defeated! +10 points — total_score = {}",
+                    self.total_score
+                );
+            }
+        }
+    }
 }
 
 impl Plugin for Game {
     fn register(&self, ctx: PluginRegistrationContext) {
-        let ctors = &ctx.serialization_context.script_constructors;
-        ctors.add::<crate::Player>("Player");
-        ctors.add::<Bot>("Bot");
+        register_scripts(&ctx.serialization_context.script_constructors);
     }
 
     fn init(&mut self, scene_path: Option<&str>, ctx: PluginContext) {
-        ctx.async_scene_loader.request(scene_path.unwrap_or("data/scene.rgs"));
+        self.input = InputStream::from_env();
+        let seed = self.input.replay_seed().unwrap_or_else(rng::startup_seed);
+        self.input.set_seed(seed);
+        self.rng = GameRng::new(seed);
+        Log::info(format!(
+            "RNG seed: {} (set {} to replay this run)",
+            self.rng.seed(),
+            rng::SEED_VAR
+        ));
+        self.load_config();
+        self.save_dir = Some(save::SAVE_DIR.into());
+        self.high_scores = HighScoreTable::load(HIGH_SCORES_PATH).unwrap_or_else(|err| {
+            Log::err(format!("{HIGH_SCORES_PATH}: {err}"));
+            HighScoreTable::default()
+        });
+        self.high_scores_path = Some(HIGH_SCORES_PATH.into());
+        self.gamepads = Gamepads::init();
+        self.hud = Some(Hud::new(ctx.user_interfaces.first_mut()));
+        self.menu = Some(Menu::new(ctx.user_interfaces.first_mut()));
+        self.state = UiState::MainMenu;
+        // A scene given by the editor is played instead of the first level.
+        if let Some(path) = scene_path {
+            self.scene_path = path.into();
+        } else if self.levels.is_empty() {
+            self.scene_path = DEFAULT_SCENE.into();
+        }
+        ctx.async_scene_loader.request(&self.scene_path);
     }
 
     fn on_scene_loaded(
@@ -108,38 +645,94 @@ impl Plugin for Game {
             context.scenes.remove(self.scene);
         }
         self.scene = scene;
-        self.bot_spawn_timer = 0.0;
+        self.prepare_scene(&mut context.scenes[scene], context.resource_manager);
+    }
+
+    fn on_deinit(&mut self, _context: PluginContext) {
+        self.input.finish();
     }
 
     fn update(&mut self, context: &mut PluginContext) {
-        if let Some(scene) = context.scenes.try_get_mut(self.scene) {
-            let graph = &mut scene.graph;
-            let dt = context.dt;
-            self.bot_spawn_timer += dt;
-
-            // Only act every 10 seconds
-            if self.bot_spawn_timer >= 10.0 {
-                self.bot_spawn_timer = 0.0;
-
-                // Find the first invisible Skeleton bot
-                if let Some((handle, node)) = graph
-                    .pair_iter_mut()
-                    .find(|(_, node)| node.name().starts_with("Skeleton") && !node.visibility())
-                {
-                    node.local_transform_mut().set_position(Vector3::new(0.0, 0.0, 0.0)); // Bot will be placed at 0 0 when first spawned
-                    println!(
-                            "▶ {:?} first spawned at ({:.2}, {:.2})",
-                            node.name(),
-                            node.local_transform().position().x,
-                            node.local_transform().position().y
-                        );
-                    node.set_visibility(true); // Make the bot visible
+        for (action, pressed) in self.gamepads.poll(&self.bindings) {
+            self.input.push(action, pressed);
+        }
+        if self.restart_requested {
+            // The old run is dropped at once; the new one starts once the scene is loaded again.
+            context.scenes.remove(self.scene);
+            self.scene = Handle::NONE;
+            self.reset_run();
+            context.async_scene_loader.request(&self.scene_path);
+        }
+        if self.next_level_requested {
+            context.scenes.remove(self.scene);
+            self.scene = Handle::NONE;
+            self.next_level();
+            context.async_scene_loader.request(&self.scene_path);
+        }
+        if let Some(path) = self.load_requested.take() {
+            let serialization_context = context.serialization_context.clone();
+            match save::load_run(&path, serialization_context, context.resource_manager, self) {
+                Ok(scene) => {
+                    context.scenes.remove(self.scene);
+                    self.scene = context.scenes.add(scene);
+                    println!("▶ Game loaded from {}", path.display());
                 }
+                Err(err) => Log::err(format!("{}: {err}", path.display())),
+            }
+        }
+        if let Some(scene) = context.scenes.try_get_mut(self.scene) {
+            self.update_scene(scene, context.resource_manager, context.dt);
+            let state = HudState::collect(self, scene);
+            if let Some(hud) = &mut self.hud {
+                hud.update(context.user_interfaces.first(), state);
+            }
+        }
+        if let Some(menu) = &mut self.menu {
+            menu.show(
+                context.user_interfaces.first(),
+                self.state,
+                self.total_score,
+                &self.high_scores,
+                self.new_high_score,
+                self.victory,
+            );
+        }
+        if self.quit_requested {
+            // Closing the event loop deinitializes the plugins like closing the window does.
+            if let Some(window_target) = context.window_target {
+                window_target.exit();
             }
         }
     }
 
+    fn on_ui_message(&mut self, _context: &mut PluginContext, message: &UiMessage) {
+        if let Some(TextMessage::Text(text)) = message.data() {
+            let name_box = self.menu.as_ref().map(|m| m.name_box);
+            if name_box == Some(message.destination())
+                && message.direction() == MessageDirection::FromWidget
+            {
+                self.player_name.clone_from(text);
+            }
+        }
+        if let Some(ButtonMessage::Click) = message.data() {
+            if let Some(action) = self
+                .menu
+                .as_ref()
+                .and_then(|m| m.action(message.destination()))
+            {
+                self.input.push(action, true);
+                self.input.push(action, false);
+            }
+        }
+    }
 
+    fn on_os_event(&mut self, event: &Event<()>, _context: PluginContext) {
+        if let Some((key, pressed)) = keyboard_input(event) {
+            for action in self.bindings.key_actions(key) {
+                self.input.push(action, pressed);
+            }
+        }
+    }
 }
 
 
@@ -159,21 +752,38 @@ pub struct Player {
     animations: Vec<SpriteSheetAnimation>,
     current_animation: u32,
 
-    pub health: f32,
-    max_health: f32,
+    pub health: Health,
     health_fill_handle: Handle<Node>,
 
-    initial_position: Vector2<f32>,
-
-    item_timer: Option<f32>,
-    bomb_timer: f32,
-    last_health: f32,
-    heart_pulse_timer: f32,
-    explosion_timer: Option<f32>,
-
-    fire_timer: Option<f32>,        // None when no fire is active
-    fire_tick_accum: f32,           // accumulates dt until ≥1.0 to deal next tick
-
+    pickups: Vec<Pickup>,
+    active_effects: Vec<ActiveEffect>,
+    spawn_timers: HashMap<String, f32>,
+    pulse_timer: f32,
+
+    speed_multiplier: f32,
+    speed_boost_timer: f32,
+
+    combo: Combo,
+    /// Index in `combo.attacks` of the last swing, `None` before the first one.
+    combo_step: Option<usize>,
+    /// Seconds since the last swing started.
+    since_swing: f32,
+    /// Seconds since the last shot of a ranged weapon.
+    since_shot: f32,
+    /// Direction the player faces along X: 1.0 towards +X (screen left), -1.0 towards -X.
+    facing: f32,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    attack_animations: Vec<SpriteSheetAnimation>,
+
+    dodge: Dodge,
+    dodge_direction: Vector2<f32>,
+    /// Seconds left of the current slide.
+    dodge_timer: f32,
+    dodge_cooldown_timer: f32,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    slide_animation: SpriteSheetAnimation,
 
     pub has_printed_game_over: bool,
 }
@@ -189,17 +799,26 @@ impl Default for Player {
             game_over: false,
             animations: Default::default(),
             current_animation: 0,
-            max_health: 100.0,
-            health: 100.0,
+            health: Health::new(100.0),
             health_fill_handle: Handle::NONE,
-            initial_position: Vector2::new(0.0, 0.0),
-            item_timer: None,
-            bomb_timer: 0.0,
-            last_health: 100.0,
-            heart_pulse_timer: 0.0,
-            explosion_timer: None,
-            fire_timer: None,
-            fire_tick_accum: 0.0,
+            pickups: Default::default(),
+            active_effects: Default::default(),
+            spawn_timers: Default::default(),
+            pulse_timer: 0.0,
+            speed_multiplier: 1.0,
+            speed_boost_timer: 0.0,
+            combo: Default::default(),
+            combo_step: None,
+            since_swing: 0.0,
+            since_shot: f32::MAX,
+            // The adventurer sprite looks towards screen right when not mirrored.
+            facing: -1.0,
+            attack_animations: Default::default(),
+            dodge: Default::default(),
+            dodge_direction: Vector2::new(0.0, 0.0),
+            dodge_timer: 0.0,
+            dodge_cooldown_timer: 0.0,
+            slide_animation: Default::default(),
             has_printed_game_over: false,
         }
     }
@@ -207,419 +826,350 @@ impl Default for Player {
 
 impl Player {
 
-    /// Spawn bomb at explicit `pos`
-    pub fn spawn_item(
-        &self,
-        context: &mut ScriptContext,
-        pos: Vector2<f32>
-    ) -> Handle<Node> {
-        let bomb_texture = context.resource_manager.request::<Texture>("data/bomb.png");
-        let bomb = RectangleBuilder::new(
-            BaseBuilder::new()
-                .with_local_transform(
-                    TransformBuilder::new()
-                        .with_local_position(Vector3::new(pos.x, pos.y, 0.0))
-                        .with_local_scale(Vector3::new(0.7, 0.7, 0.7))
-                        .build(),
-                ),
-        )
-        .build(&mut context.scene.graph);
-
-        if let Some(rect) = context.scene.graph.try_get_mut(bomb)
-                            .and_then(|n| n.cast_mut::<Rectangle>()) {
-            rect.material().data_ref().bind("diffuseTexture", bomb_texture);
-        }
-
-        println!("Bomb spawned at: {:?}", pos);
-        bomb
+    /// Whether damage is currently ignored, thanks to a shield or a dodge.
+    pub fn is_invulnerable(&self) -> bool {
+        self.health.is_invulnerable()
     }
 
-    /// Spawn fire at explicit `pos`
-    pub fn spawn_fire(
-        &self,
-        context: &mut ScriptContext,
-        pos: Vector2<f32>
-    ) -> Handle<Node> {
-        let fire_texture = context.resource_manager.request::<Texture>("data/fire.png");
-        let fire = RectangleBuilder::new(
-            BaseBuilder::new()
-                .with_local_transform(
-                    TransformBuilder::new()
-                        .with_local_position(Vector3::new(pos.x, pos.y, 0.0))
-                        .with_local_scale(Vector3::new(0.8, 0.8, 0.8))
-                        .build(),
-                ),
-        )
-        .build(&mut context.scene.graph);
-
-        if let Some(rect) = context.scene.graph.try_get_mut(fire)
-                            .and_then(|n| n.cast_mut::<Rectangle>()) {
-            rect.material().data_ref().bind("diffuseTexture", fire_texture);
-        }
-
-        println!("Fire spawned at: {:?}", pos);
-        fire
+    pub fn is_dodging(&self) -> bool {
+        self.dodge_timer > 0.0
     }
 
+    pub fn is_attacking(&self) -> bool {
+        self.attack_animations.iter().any(|a| a.is_playing())
+    }
 
-    pub fn spawn_heart(&self, context: &mut ScriptContext) -> Handle<Node> {
-        let player_position = context.scene.graph[self.sprite]
-            .global_position()
-            .xy();
-
-        let mut rng = rand::thread_rng();
-        let offset_x: f32 = rng.gen_range(-5.0..=5.0);
-        let offset_y: f32 = rng.gen_range(-5.0..=5.0);
-        let mut heart_position = Vector2::new(player_position.x + offset_x, player_position.y + offset_y);
-        heart_position.x = heart_position.x.clamp(-11.0, 11.0);
-        heart_position.y = heart_position.y.clamp(-4.0, 17.0);
+    /// Item effects still running.
+    pub fn active_effects(&self) -> &[ActiveEffect] {
+        &self.active_effects
+    }
 
-        let heart_texture = context.resource_manager.request::<Texture>("data/heart.png");
+    /// Seconds since the last spawn of the periodic item `item`.
+    pub fn spawn_timer(&self, item: &str) -> f32 {
+        self.spawn_timers.get(item).copied().unwrap_or(0.0)
+    }
 
-        let heart = RectangleBuilder::new(
-            BaseBuilder::new()
-                .with_local_transform(
-                    TransformBuilder::new()
-                        .with_local_position(Vector3::new(heart_position.x, heart_position.y, 0.0))
-                        .with_local_scale(Vector3::new(0.7, 0.7, 0.7))
-                        .build(),
-                ),
-        )
-        .build(&mut context.scene.graph);
+    /// Index in the combo of the last swing.
+    pub fn combo_step(&self) -> Option<usize> {
+        self.combo_step
+    }
 
-        if let Some(rectangle) = context.scene.graph.try_get_mut(heart).and_then(|n| n.cast_mut::<Rectangle>()) {
-            let material = rectangle.material();
-            material.data_ref().bind("diffuseTexture", heart_texture);
+    /// Swings at every bot in front of the player with the next attack of the combo, unless the
+    /// previous swing is still cooling down. Returns the bots that were hit.
+    fn swing(&mut self, context: &mut TickContext) -> Vec<Handle<Node>> {
+        if self.game_over || self.is_dodging() {
+            return Vec::new();
+        }
+        let Some(step) = self.combo.next_step(self.combo_step, self.since_swing) else {
+            return Vec::new();
+        };
+        self.combo_step = Some(step);
+        self.since_swing = 0.0;
+        for (i, animation) in self.attack_animations.iter_mut().enumerate() {
+            animation.stop();
+            if i == step {
+                animation.play();
+            }
         }
 
-        println!("Heart spawned at: {:?}", heart_position);
-
-        heart
+        let attack = &self.combo.attacks[step];
+        let position = context.scene.graph[context.handle].global_position().xy();
+        let hitbox = attack.hitbox(position, self.facing);
+        let events = &mut context.game.events;
+        combat::hit_bots(
+            &context.scene.graph,
+            events,
+            context.handle,
+            &hitbox,
+            attack.damage,
+        )
     }
 
-    fn update_health_bar(&mut self, context: &mut ScriptContext) {
-        if self.health_fill_handle.is_some() {
-            let health_ratio = self.health / self.max_health;
-            let full_width = 100.0;
-
-            if let Some(health_fill_rect) = context.scene.graph.try_get_mut(self.health_fill_handle).and_then(|n| n.cast_mut::<Rectangle>()) {
-                let mut local_transform = health_fill_rect.local_transform_mut();
-                local_transform.set_scale(Vector3::new(health_ratio, local_transform.scale().y, local_transform.scale().z));
-            }
-
-            if let Some(health_fill_rect) = context.scene.graph.try_get_mut(self.health_fill_handle).and_then(|n| n.cast_mut::<Rectangle>()) {
-                let mut local_transform = health_fill_rect.local_transform_mut();
-                local_transform.set_position(Vector3::new((full_width - health_ratio * full_width) / 200.0, local_transform.scale().y, local_transform.scale().z));
-            }
+    /// Shoots with the ranged weapon the player holds, or swings without one.
+    fn attack(&mut self, context: &mut TickContext) {
+        if !self.shoot(context) {
+            self.swing(context);
         }
     }
-}
 
-impl ScriptTrait for Player {
-    fn on_start(&mut self, ctx: &mut ScriptContext) {
-        ctx.plugins.get_mut::<Game>().player = ctx.handle;
-
-        self.max_health = 100.0;
-        self.health = self.max_health;
-    }
-
-    fn on_os_event(&mut self, event: &Event<()>, context: &mut ScriptContext) {
-        if let Event::WindowEvent { event, .. } = event {
-            if let WindowEvent::KeyboardInput { event, .. } = event {
-                if let PhysicalKey::Code(keycode) = event.physical_key {
-                    let pressed = event.state == ElementState::Pressed;
-
-                    match event.physical_key {
-                        PhysicalKey::Code(KeyCode::KeyA) | PhysicalKey::Code(KeyCode::ArrowLeft)=> self.move_left = pressed,
-                        PhysicalKey::Code(KeyCode::KeyD) | PhysicalKey::Code(KeyCode::ArrowRight)=> self.move_right = pressed,
-                        PhysicalKey::Code(KeyCode::KeyW) | PhysicalKey::Code(KeyCode::ArrowUp)=> self.move_up = pressed,
-                        PhysicalKey::Code(KeyCode::KeyS) | PhysicalKey::Code(KeyCode::ArrowDown)=> self.move_down = pressed,
-                        PhysicalKey::Code(KeyCode::Space) if pressed => {
-                            // Reduce health by 20 when space is pressed
-                            self.health = (self.health - 20.0).max(0.0); // Ensure health doesn't go below 0
-                        },
-                        PhysicalKey::Code(KeyCode::KeyR) if pressed && self.game_over => {
-                            // Reset health to max when R is pressed
-                            self.health = self.max_health;
-                            self.game_over = false; // Reset game over state
-                            self.has_printed_game_over = false;
-                            // Reset the player's position to the starting point
-                            if let Some(node) = context.scene.graph.try_get_mut(context.handle) {
-                                node.local_transform_mut().set_position(Vector3::new(
-                                    self.initial_position.x - 1.0,
-                                    self.initial_position.y - 4.0,
-                                    0.0,
-                                ));
-                            }
-                            println!("Game Restarted! Health reset to {}", self.health);
-                        },
-                        PhysicalKey::Code(KeyCode::Escape) if pressed && self.game_over => {
-                            // Exit the game when Escape is pressed
-                            println!("Exiting game...");
-                            std::process::exit(0);
-                        },
-                        _ => {}
-                    }
-                }
-            }
+    /// Fires the ranged weapon the player holds, if any, unless its previous shot is still
+    /// cooling down. Returns whether the player holds one, in which case the attack doesn't swing.
+    fn shoot(&mut self, context: &mut TickContext) -> bool {
+        let items = context.game.items.clone();
+        let weapon = self
+            .active_effects
+            .iter()
+            .filter_map(|e| items.get(&e.item))
+            .find_map(|d| match &d.effect {
+                ItemEffect::RangedWeapon {
+                    damage,
+                    cooldown,
+                    projectile,
+                    ..
+                } => Some((*damage, *cooldown, projectile)),
+                _ => None,
+            });
+        let Some((damage, cooldown, spec)) = weapon else {
+            return false;
+        };
+        if self.game_over || self.is_dodging() || self.since_shot < cooldown {
+            return true;
         }
+        self.since_shot = 0.0;
+        let position = context.scene.graph[context.handle].global_position().xy();
+        let direction = self.aim_direction();
+        let owner = context.handle;
+        projectile::fire(
+            spec,
+            damage,
+            Faction::Player,
+            owner,
+            position,
+            direction,
+            context,
+        );
+        true
     }
 
-    fn on_update(&mut self, context: &mut ScriptContext) {
-        self.update_health_bar(context);
+    /// Direction of movement, or the facing direction when standing still.
+    fn aim_direction(&self) -> Vector2<f32> {
+        let input = Vector2::new(
+            (self.move_left as i8 - self.move_right as i8) as f32,
+            (self.move_up as i8 - self.move_down as i8) as f32,
+        );
+        input
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(|| Vector2::new(self.facing, 0.0))
+    }
 
-        // Check if health is 0 or below and print the "Game Over" message only once
-        if self.health <= 0.0 && !self.has_printed_game_over {
-            self.game_over = true;
-            self.has_printed_game_over = true; // Mark that the message has been printed
-            // Print the game over message once
-            println!("❤︎❤︎❤︎ Game Over! Press R to Restart or Esc to Exit.");
+    /// Starts a slide in the direction of movement, or the facing direction when standing still.
+    fn start_dodge(&mut self) {
+        if self.game_over || self.is_dodging() || self.dodge_cooldown_timer > 0.0 {
             return;
         }
+        self.dodge_direction = self.aim_direction();
+        self.dodge_timer = self.dodge.duration;
+        self.dodge_cooldown_timer = self.dodge.cooldown;
+        self.health.make_invulnerable(self.dodge.invulnerability);
+        self.slide_animation.rewind_to_beginning();
+        self.slide_animation.play();
+    }
 
-        if self.game_over {
-            return;
+    fn update_dodge(&mut self, dt: f32) {
+        self.dodge_cooldown_timer = (self.dodge_cooldown_timer - dt).max(0.0);
+        if self.dodge_timer > 0.0 {
+            self.dodge_timer = (self.dodge_timer - dt).max(0.0);
+            if self.dodge_timer == 0.0 {
+                self.slide_animation.stop();
+            }
         }
+    }
 
-        // Animate the heart's pulsing effect
-        self.heart_pulse_timer += context.dt;
-        let pulse_scale = 0.7 + 0.1 * (self.heart_pulse_timer * 3.0).sin(); // Oscillates between 0.6 and 0.8
-        // Animate the bomb's pulsing effect
-        let bomb_pulse_scale = 0.7 + 0.05 * (self.heart_pulse_timer * 5.0).sin(); // Oscillates between 0.65 and 0.75
-
-
-        let heart_handle = context
-            .scene
-            .graph
-            .pair_iter_mut()
-            .find(|(_, node)| node.name() == "Heart" && node.visibility())
-            .map(|(handle, _)| handle);
-
-        if let Some(hh) = heart_handle {
-            let player_pos = context.scene.graph[self.sprite].global_position().xy();
-            let heart_pos = context.scene.graph[hh].global_position().xy();
-
-            if let Some(heart_node) = context.scene.graph.try_get_mut(hh) {
-                heart_node
-                    .local_transform_mut()
-                    .set_scale(Vector3::new(pulse_scale, pulse_scale, pulse_scale));
+    /// Picks a spawn point for `definition` around the player, keeping clear of other pickups.
+    fn item_spawn_point(
+        &self,
+        definition: &ItemDefinition,
+        player_pos: Vector2<f32>,
+        graph: &Graph,
+        arena: &Arena,
+        rng: &mut impl Rng,
+    ) -> Vector2<f32> {
+        let (min_r, max_r) = definition.spawn.distance_range();
+        let taken: Vec<Vector2<f32>> = self
+            .pickups
+            .iter()
+            .filter_map(|p| graph.try_get(p.node))
+            .map(|n| n.global_position().xy())
+            .collect();
+
+        let mut pos = random_point_around(player_pos, min_r, max_r, arena, rng);
+        for _ in 0..MAX_PLACEMENT_ATTEMPTS {
+            if taken.iter().all(|t| (t - pos).norm() >= MIN_SEPARATION) {
+                break;
             }
+            pos = random_point_around(player_pos, min_r, max_r, arena, rng);
+        }
+        pos
+    }
 
-            if (player_pos - heart_pos).norm() < 1.0 {
-                if let Some(node) = context.scene.graph.try_get_mut(hh) {
-                    node.set_visibility(false);
+    /// Spawns, animates and collects pickups, and advances the effects of collected ones.
+    fn update_items(&mut self, context: &mut TickContext) {
+        let items = context.game.items.clone();
+        let dt = context.dt;
+        let player_pos = context.scene.graph[self.sprite].global_position().xy();
+        self.pulse_timer += dt;
+
+        for definition in items.iter() {
+            let due = match definition.spawn {
+                SpawnRule::LowHealth { threshold, .. } => {
+                    self.health.current() < threshold
+                        && !self.pickups.iter().any(|p| p.item == definition.name)
                 }
-                self.health = (self.health + 30.0).min(self.max_health);
-                self.item_timer = None;
-                self.last_health = self.health;
-                println!("Heart collected! Health: {}", self.health);
-            }
-        } else if self.health < 50.0 && self.item_timer.is_none() {
-            let heart = self.spawn_heart(context);
-            context.scene.graph[heart].set_name("Heart");
-            context.scene.graph[heart].set_visibility(true);
-            self.item_timer = Some(0.0);
-        }
-
-        if let Some(timer) = &mut self.item_timer {
-            *timer += context.dt;
-
-            if *timer >= 5.0 {
-                let heart_handle = context
-                    .scene
-                    .graph
-                    .pair_iter_mut()
-                    .find(|(_, node)| node.name() == "Heart" && node.visibility())
-                    .map(|(handle, _)| handle);
-
-                if let Some(heart) = heart_handle {
-                    if let Some(node) = context.scene.graph.try_get_mut(heart) {
-                        node.set_visibility(false);
+                SpawnRule::Periodic { interval, .. } => {
+                    let timer = self
+                        .spawn_timers
+                        .entry(definition.name.clone())
+                        .or_default();
+                    *timer += dt;
+                    if *timer >= interval {
+                        *timer = 0.0;
+                        true
+                    } else {
+                        false
                     }
                 }
+            };
 
-                self.item_timer = None;
+            if due {
+                let pos = self.item_spawn_point(
+                    definition,
+                    player_pos,
+                    &context.scene.graph,
+                    &context.game.arena,
+                    &mut context.game.rng,
+                );
+                let node = item::spawn_item(definition, pos, context);
+                self.pickups.push(Pickup {
+                    item: definition.name.clone(),
+                    node,
+                    age: 0.0,
+                });
             }
         }
 
-        self.bomb_timer += context.dt;
-
-        if self.bomb_timer >= 30.0 {
-            let player_pos = context.scene.graph[self.sprite].global_position().xy();
-            let mut rng = rand::thread_rng();
-
-            // Compute bomb position
-            let bomb_pos = random_point_around(
-                player_pos,
-                MIN_DISTANCE_FROM_PLAYER,
-                MAX_DISTANCE_FROM_PLAYER,
-                &mut rng,
-            );
-            let bomb = self.spawn_item(context, bomb_pos);
-            context.scene.graph[bomb].set_name("Bomb");
-            context.scene.graph[bomb].set_visibility(true);
-
-            // Compute fire position, ensure separation from bomb
-            let fire_pos = loop {
-                let p = random_point_around(
-                    player_pos,
-                    MIN_DISTANCE_FROM_PLAYER,
-                    MAX_DISTANCE_FROM_PLAYER,
-                    &mut rng,
-                );
-                if (p - bomb_pos).norm() >= MIN_SEPARATION {
-                    break p;
-                }
+        for mut pickup in std::mem::take(&mut self.pickups) {
+            let Some(definition) = items.get(&pickup.item) else {
+                context.scene.graph.remove_node(pickup.node);
+                continue;
+            };
+            let Some(node) = context.scene.graph.try_get_mut(pickup.node) else {
+                continue;
             };
-            let fire = self.spawn_fire(context, fire_pos);
-            context.scene.graph[fire].set_name("Fire");
-            context.scene.graph[fire].set_visibility(true);
-
-            self.bomb_timer = 0.0;
-        }
 
-        let bomb_handle = context
-            .scene
-            .graph
-            .pair_iter_mut()
-            .find(|(_, node)| node.name() == "Bomb" && node.visibility())
-            .map(|(handle, _)| handle);
-
-        if let Some(bh) = bomb_handle {
-            let player_pos = context.scene.graph[self.sprite].global_position().xy();
-            let bomb_pos = context.scene.graph[bh].global_position().xy();
-
-            // Only pulse if we're not in the middle of an explosion
-            if self.explosion_timer.is_none() {
-                let bomb_pulse_scale = 0.7 + 0.05 * (self.heart_pulse_timer * 5.0).sin();
-                if let Some(bomb_node) = context.scene.graph.try_get_mut(bh) {
-                    bomb_node
-                        .local_transform_mut()
-                        .set_scale(Vector3::new(bomb_pulse_scale, bomb_pulse_scale, bomb_pulse_scale));
+            pickup.age += dt;
+            let pulse = definition.scale
+                + definition.pulse.amplitude
+                    * (self.pulse_timer * definition.pulse.frequency).sin();
+            node.local_transform_mut()
+                .set_scale(Vector3::new(pulse, pulse, pulse));
+
+            // Pickups are root children, and the global position of one spawned this frame is
+            // not computed yet.
+            let item_pos = node.local_transform().position().xy();
+            if (player_pos - item_pos).norm() < definition.pickup_radius {
+                println!("{} collected!", definition.name);
+                context.game.events.publish(ItemCollected {
+                    item: definition.name.clone(),
+                    collector: context.handle,
+                });
+                let effect = &definition.effect;
+                let duration = effect.on_collected(pickup.node, self, context);
+                let keep_node = duration.is_some() && effect.keeps_pickup();
+                if !keep_node {
+                    context.scene.graph.remove_node(pickup.node);
                 }
+                if let Some(time_left) = duration {
+                    self.active_effects.push(ActiveEffect {
+                        item: pickup.item,
+                        node: if keep_node { pickup.node } else { Handle::NONE },
+                        time_left,
+                        tick_accum: 0.0,
+                    });
+                }
+            } else if definition.lifetime.is_some_and(|l| pickup.age >= l) {
+                context.scene.graph.remove_node(pickup.node);
+            } else {
+                self.pickups.push(pickup);
             }
+        }
 
-
-            if (player_pos - bomb_pos).norm() < 1.0 {
-                if self.explosion_timer.is_none() {
-                    println!("Bomb exploded!");
-            
-                    // Change the bomb's texture to explosion.png
-                    let explosion_texture = context.resource_manager.request::<Texture>("data/explosion.png");
-                    if let Some(bomb_node) = context.scene.graph.try_get_mut(bh).and_then(|n| n.cast_mut::<Rectangle>()) {
-
-                        let material = bomb_node.material();
-                        material.data_ref().bind("diffuseTexture", explosion_texture);
-                        
-                        // Adjust the scale of the rectangle to make the texture appear larger
-                        bomb_node
-                        .local_transform_mut()
-                        .set_scale(Vector3::new(1.5, 1.5, 1.0)); // Scale the rectangle (1.5x larger)
-
-
-                    println!("Explosion scale set to: {:?}, UV rect set to full texture.", bomb_node.local_transform().scale());
-                    
-                    }
-            
-                    // Start the explosion timer
-                    self.explosion_timer = Some(0.5);
-            
-                    // Damage bots within the explosion radius
-                    let explosion_radius = 6.0; // Adjust radius as needed
-                    let bots_to_hit: Vec<_> = context
-                        .scene
-                        .graph
-                        .pair_iter_mut()
-                        .filter_map(|(h, node)| {
-                            let bot_pos = node.global_position().xy();
-            
-                            if let Some(bot_script) = node.script_mut(0).and_then(|s| s.cast_mut::<Bot>()) {
-                                let distance = (bot_pos - bomb_pos).norm();
-                                if distance <= explosion_radius {
-                                    println!("Bot found at position: {:?}, distance: {}", bot_pos, distance);
-                                    return Some((h, bot_script, distance));
-                                }
-                            }
-                            None
-                        })
-                        .collect();
-            
-                    for (bot_h, bot_script, distance) in bots_to_hit {
-                        let damage = if distance <= 3.0 {
-                            100.0
-                        } else if distance <= 4.0 {
-                            70.0
-                        } else if distance <= 5.0 {
-                            40.0
-                        } else if distance <= 6.0 {
-                            10.0
-                        } else {
-                            0.0
-                        };
-
-                        let new_health = (bot_script.get_health() - damage).max(0.0);
-                        bot_script.set_health(new_health);
-                        println!(
-                            "Bot at distance {:.2} damaged by bomb! Damage: {}, Remaining health: {}",
-                            distance, damage, new_health
-                        );
-                    }
-                }
+        for mut effect in std::mem::take(&mut self.active_effects) {
+            if let Some(definition) = items.get(&effect.item) {
+                definition.effect.on_active(&mut effect, self, context);
             }
-            
-            // Check if the explosion timer is active and update it
-            if let Some(timer) = &mut self.explosion_timer {
-                *timer -= context.dt;
-                if *timer <= 0.0 {
-                    // Hide the bomb after the timer expires
-                    context.scene.graph[bh].set_visibility(false);
-                    self.explosion_timer = None; // Reset the timer
+            effect.time_left -= dt;
+            if effect.time_left > 0.0 {
+                self.active_effects.push(effect);
+            } else {
+                println!("{} effect ended", effect.item);
+                if context.scene.graph.is_valid_handle(effect.node) {
+                    context.scene.graph.remove_node(effect.node);
                 }
             }
         }
+    }
 
-        // 1) Handle existing fire on the map (pulsing + pickup)
-        let fire_handle = context.scene.graph
-            .pair_iter_mut()
-            .find(|(_, node)| node.name() == "Fire" && node.visibility())
-            .map(|(h, _)| h);
-        if let Some(fh) = fire_handle {
-            // Pulse
-            let pulse = 0.8 + 0.1 * (self.heart_pulse_timer * 5.0).sin();
-            if let Some(n) = context.scene.graph.try_get_mut(fh) {
-                n.local_transform_mut().set_scale(Vector3::new(pulse, pulse, 1.0));
-            }
-            // Pickup
-            let pp = context.scene.graph[self.sprite].global_position().xy();
-            let fp = context.scene.graph[fh].global_position().xy();
-            if (pp - fp).norm() < 1.0 {
-                println!("🔥 Fire activated!");
-                context.scene.graph[fh].set_visibility(false);
-                self.fire_timer = Some(8.0);
-                self.fire_tick_accum = 0.0;
+    /// Counts down the speed boost granted by pickups.
+    fn update_timed_boosts(&mut self, dt: f32) {
+        if self.speed_boost_timer > 0.0 {
+            self.speed_boost_timer -= dt;
+            if self.speed_boost_timer <= 0.0 {
+                self.speed_multiplier = 1.0;
             }
         }
+    }
 
-        // 2) Handle fire damage‐over‐time
-        if let Some(time_left) = &mut self.fire_timer {
-            *time_left -= context.dt;
-            self.fire_tick_accum += context.dt;
-            while self.fire_tick_accum >= 1.0 {
-                self.fire_tick_accum -= 1.0;
-                println!("🔥 Fire tick: 5 damage to all bots");
-                for (_h, node) in context.scene.graph.pair_iter_mut() {
-                    if let Some(bot) = node.script_mut(0).and_then(|s| s.cast_mut::<Bot>()) {
-                        let hp = (bot.get_health() - 5.0).max(0.0);
-                        bot.set_health(hp);
-                    }
+    fn update_health_bar(&mut self, context: &mut TickContext) {
+        self.health
+            .update_bar(&mut context.scene.graph, self.health_fill_handle);
+    }
+}
+
+impl Player {
+    pub fn start(&mut self, ctx: &mut TickContext) {
+        ctx.game.player = ctx.handle;
+
+        self.attack_animations = self
+            .combo
+            .attacks
+            .iter()
+            .map(|a| {
+                let rm = ctx.resource_manager;
+                combat::sheet_animation(rm, a.first_frame, a.last_frame, combat::ATTACK_FPS)
+            })
+            .collect();
+        self.slide_animation = combat::sheet_animation(
+            ctx.resource_manager,
+            self.dodge.first_frame,
+            self.dodge.last_frame,
+            combat::ATTACK_FPS,
+        );
+        self.slide_animation.set_looping(true);
+    }
+
+    pub fn handle_action(&mut self, action: Action, pressed: bool, context: &mut TickContext) {
+        match action {
+            Action::MoveLeft => self.move_left = pressed,
+            Action::MoveRight => self.move_right = pressed,
+            Action::MoveUp => self.move_up = pressed,
+            Action::MoveDown => self.move_down = pressed,
+            Action::Attack if pressed => self.attack(context),
+            Action::Dodge if pressed => self.start_dodge(),
+            Action::DebugDamage if pressed => {
+                // Reduce health by 20 when space is pressed, shield or not
+                let health = self.health.current() - 20.0;
+                if let Some(change) = self.health.set(health) {
+                    self.apply_health_change(change, context);
                 }
             }
-            if *time_left <= 0.0 {
-                println!("🔥 Fire effect ended");
-                self.fire_timer = None;
-            }
+            _ => {}
+        }
+    }
+
+    pub fn update(&mut self, context: &mut TickContext) {
+        self.update_health(context);
+        self.update_health_bar(context);
+
+        if self.game_over {
+            return;
         }
 
+        self.update_items(context);
+        self.update_timed_boosts(context.dt);
+        self.since_swing += context.dt;
+        self.since_shot += context.dt;
+        self.update_dodge(context.dt);
+
         // The script can be assigned to any scene node, but we assert that it will work only with
         // 2d rigid body nodes.
         if let Some(rigid_body) = context.scene.graph[context.handle].cast_mut::<RigidBody>() {
@@ -637,10 +1187,18 @@ impl ScriptTrait for Player {
             };
 
             // Set the linear velocity of the rigid body based on the state of the player
-            rigid_body.set_lin_vel(Vector2::new(x_speed, y_speed));
+            if self.is_dodging() {
+                rigid_body.set_lin_vel(self.dodge_direction * self.dodge.speed);
+            } else {
+                rigid_body.set_lin_vel(Vector2::new(x_speed, y_speed) * self.speed_multiplier);
+            }
             // ...
             // ANCHOR_END: on_update_begin
 
+            if x_speed != 0.0 {
+                self.facing = x_speed.signum();
+            }
+
             // ANCHOR: sprite_scaling
             // It is always a good practice to check whether the handles are valid, at this point we don't know
             // for sure what's the value of the sprite field. It can be unassigned and the following code won't
@@ -681,7 +1239,14 @@ impl ScriptTrait for Player {
         // ANCHOR_END: on_update_closing_bracket_2
 
         // ANCHOR: applying_animation
-        if let Some(current_animation) = self.animations.get_mut(self.current_animation as usize) {
+        // A swing plays over the run and idle animations until its last frame, a slide until
+        // the dodge ends.
+        let animation = match self.attack_animations.iter_mut().find(|a| a.is_playing()) {
+            Some(attack) => Some(attack),
+            None if self.slide_animation.is_playing() => Some(&mut self.slide_animation),
+            None => self.animations.get_mut(self.current_animation as usize),
+        };
+        if let Some(current_animation) = animation {
             current_animation.update(context.dt);
 
             if let Some(sprite) = context
@@ -711,4 +1276,53 @@ impl ScriptTrait for Player {
         // ANCHOR: on_update_closing
         
     }
-}
\ No newline at end of file
+}
+
+impl Damageable for Player {
+    fn health(&self) -> &Health {
+        &self.health
+    }
+
+    fn health_mut(&mut self) -> &mut Health {
+        &mut self.health
+    }
+
+    fn on_damaged(&mut self, _hit: &DamageDealt, _ctx: &mut TickContext) {
+        println!("▶ Player hit! Health = {}", self.health.current());
+    }
+
+    fn on_death(&mut self, context: &mut TickContext) {
+        if self.has_printed_game_over {
+            return;
+        }
+        self.game_over = true;
+        self.has_printed_game_over = true; // Mark that the message has been printed
+                                           // Print the game over message once
+        println!("❤︎❤︎❤︎ Game Over!");
+        context.game.events.publish(EntityDied {
+            entity: context.handle,
+            kind: EntityKind::Player,
+        });
+        context.game.events.publish(GameOver {
+            score: context.game.total_score,
+        });
+    }
+
+    fn on_revive(&mut self, _context: &mut TickContext) {
+        self.game_over = false; // Reset game over state
+        self.has_printed_game_over = false;
+    }
+}
+
+impl ScriptTrait for Player {
+    fn on_start(&mut self, ctx: &mut ScriptContext) {
+        self.start(&mut TickContext::from_script(ctx));
+    }
+
+    fn on_update(&mut self, ctx: &mut ScriptContext) {
+        let mut ctx = TickContext::from_script(ctx);
+        if ctx.game.is_running() {
+            self.update(&mut ctx);
+        }
+    }
+}
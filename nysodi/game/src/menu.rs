@@ -0,0 +1,296 @@
+//! Main menu, pause menu, level-complete screen, high-score entry and game-over screen.
+//!
+//! [`UiState`] is the state machine of the game screens. The [`Game`](crate::Game) plugin moves
+//! between states when it receives the [`Action::Pause`], [`Action::Restart`], [`Action::Confirm`]
+//! and [`Action::Quit`] actions, and only advances the simulation while [`UiState::Playing`]. Menu
+//! buttons don't change the state themselves: [`Menu::action`] turns a click into the action of the
+//! button, which goes through the input stream like a key press, so recorded sessions replay menu
+//! choices too.
+
+use crate::{
+    input::Action,
+    scores::{HighScoreTable, DEFAULT_NAME},
+};
+use fyrox::core::{reflect::prelude::*, visitor::prelude::*};
+use fyrox_ui::{
+    border::BorderBuilder,
+    brush::Brush,
+    button::ButtonBuilder,
+    core::{color::Color, pool::Handle},
+    message::MessageDirection,
+    stack_panel::StackPanelBuilder,
+    text::{TextBuilder, TextMessage},
+    text_box::{TextBoxBuilder, TextCommitMode},
+    widget::{WidgetBuilder, WidgetMessage},
+    BuildContext, HorizontalAlignment, Thickness, UiNode, UserInterface, VerticalAlignment,
+};
+
+const BUTTON_WIDTH: f32 = 200.0;
+const BUTTON_HEIGHT: f32 = 36.0;
+
+#[derive(Visit, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UiState {
+    /// Title screen shown at startup, before the first run.
+    MainMenu,
+    #[default]
+    Playing,
+    Paused,
+    /// The goal of a level other than the last was met; waits for the player to move on to the
+    /// next level.
+    LevelComplete,
+    /// The run made the high-score table; waits for the player to enter a name.
+    NameEntry,
+    /// The player was defeated, or completed the last level; waits for a restart.
+    GameOver,
+}
+
+impl UiState {
+    /// State reached from `self` when `action` is pressed, if the action changes the screen.
+    /// Quitting is handled by the game and doesn't change the state.
+    pub fn next(self, action: Action) -> Option<UiState> {
+        match (self, action) {
+            (UiState::MainMenu, Action::Pause) => Some(UiState::Playing),
+            (UiState::Playing, Action::Pause) => Some(UiState::Paused),
+            (UiState::Paused, Action::Pause) => Some(UiState::Playing),
+            (UiState::NameEntry, Action::Confirm) => Some(UiState::GameOver),
+            _ => None,
+        }
+    }
+
+    /// Whether [`Action::Quit`] quits the game in this state. Quitting mid-run needs a pause
+    /// first, and keys typed into the name of a high score are not commands.
+    pub fn can_quit(self) -> bool {
+        !matches!(self, UiState::Playing | UiState::NameEntry)
+    }
+
+    /// Whether [`Action::Restart`] starts a new run in this state. The game reaches
+    /// [`UiState::Playing`] once the new run is ready.
+    pub fn can_restart(self) -> bool {
+        matches!(self, UiState::Paused | UiState::GameOver)
+    }
+
+    /// Whether [`Action::QuickLoad`] replaces the run in this state: only a run in progress,
+    /// paused or not. A pending high score or level change is never dropped by a load.
+    pub fn can_load(self) -> bool {
+        matches!(self, UiState::Playing | UiState::Paused)
+    }
+}
+
+/// A menu button and the action it triggers.
+#[derive(Debug, Clone)]
+struct MenuButton {
+    button: Handle<UiNode>,
+    action: Action,
+}
+
+/// Handles of the menu widgets.
+#[derive(Debug, Clone)]
+pub struct Menu {
+    pub main_menu: Handle<UiNode>,
+    pub pause_menu: Handle<UiNode>,
+    pub level_complete: Handle<UiNode>,
+    pub name_entry: Handle<UiNode>,
+    pub game_over: Handle<UiNode>,
+    /// Score line of the level-complete screen.
+    pub level_score: Handle<UiNode>,
+    /// Rank and score line of the high-score entry screen.
+    pub new_high_score: Handle<UiNode>,
+    /// Text box the player types the name of the high score in.
+    pub name_box: Handle<UiNode>,
+    /// Title of the game-over screen, which tells a defeat from a victory.
+    pub game_over_title: Handle<UiNode>,
+    /// Final score line of the game-over screen.
+    pub final_score: Handle<UiNode>,
+    /// High-score table of the game-over screen.
+    pub high_scores: Handle<UiNode>,
+    buttons: Vec<MenuButton>,
+    /// State currently on screen.
+    shown: Option<UiState>,
+}
+
+fn title(ctx: &mut BuildContext, text: &str, size: f32) -> Handle<UiNode> {
+    TextBuilder::new(
+        WidgetBuilder::new()
+            .with_margin(Thickness::uniform(6.0))
+            .with_foreground(Brush::Solid(Color::WHITE).into()),
+    )
+    .with_text(text)
+    .with_font_size(size.into())
+    .with_horizontal_text_alignment(HorizontalAlignment::Center)
+    .with_shadow(true)
+    .build(ctx)
+}
+
+impl Menu {
+    /// Builds every menu in the middle of `ui`, all hidden.
+    pub fn new(ui: &mut UserInterface) -> Self {
+        let ctx = &mut ui.build_ctx();
+        let mut buttons = Vec::new();
+        let mut button = |ctx: &mut BuildContext, text: &str, action: Action| {
+            let button = ButtonBuilder::new(
+                WidgetBuilder::new()
+                    .with_width(BUTTON_WIDTH)
+                    .with_height(BUTTON_HEIGHT)
+                    .with_margin(Thickness::uniform(4.0)),
+            )
+            .with_text(text)
+            .build(ctx);
+            buttons.push(MenuButton { button, action });
+            button
+        };
+
+        let main_menu = [
+            title(ctx, "Nysodi", 48.0),
+            button(ctx, "Play", Action::Pause),
+            button(ctx, "Quit", Action::Quit),
+        ];
+        let pause_menu = [
+            title(ctx, "Paused", 36.0),
+            button(ctx, "Resume", Action::Pause),
+            button(ctx, "Restart", Action::Restart),
+            button(ctx, "Quit", Action::Quit),
+        ];
+        let level_score = title(ctx, "", 24.0);
+        let level_complete = [
+            title(ctx, "Level Complete!", 36.0),
+            level_score,
+            button(ctx, "Continue", Action::Confirm),
+            button(ctx, "Quit", Action::Quit),
+        ];
+        let new_high_score = title(ctx, "", 24.0);
+        let name_box = TextBoxBuilder::new(
+            WidgetBuilder::new()
+                .with_width(BUTTON_WIDTH)
+                .with_height(BUTTON_HEIGHT)
+                .with_margin(Thickness::uniform(4.0)),
+        )
+        .with_text(DEFAULT_NAME)
+        .with_text_commit_mode(TextCommitMode::Immediate)
+        .build(ctx);
+        let name_entry = [
+            title(ctx, "New High Score!", 36.0),
+            new_high_score,
+            title(ctx, "Enter your name:", 18.0),
+            name_box,
+            button(ctx, "Submit", Action::Confirm),
+        ];
+        let game_over_title = title(ctx, "Game Over", 36.0);
+        let final_score = title(ctx, "", 24.0);
+        let high_scores = title(ctx, "", 16.0);
+        let game_over = [
+            game_over_title,
+            final_score,
+            high_scores,
+            button(ctx, "Restart", Action::Restart),
+            button(ctx, "Quit", Action::Quit),
+        ];
+
+        let panel = |ctx: &mut BuildContext, children: &[Handle<UiNode>]| {
+            let content = StackPanelBuilder::new(
+                WidgetBuilder::new()
+                    .with_margin(Thickness::uniform(20.0))
+                    .with_children(children.iter().copied()),
+            )
+            .build(ctx);
+            BorderBuilder::new(
+                WidgetBuilder::new()
+                    .with_visibility(false)
+                    .with_horizontal_alignment(HorizontalAlignment::Center)
+                    .with_vertical_alignment(VerticalAlignment::Center)
+                    .with_background(Brush::Solid(Color::from_rgba(0, 0, 0, 180)).into())
+                    .with_child(content),
+            )
+            .build(ctx)
+        };
+
+        Self {
+            main_menu: panel(ctx, &main_menu),
+            pause_menu: panel(ctx, &pause_menu),
+            level_complete: panel(ctx, &level_complete),
+            name_entry: panel(ctx, &name_entry),
+            game_over: panel(ctx, &game_over),
+            level_score,
+            new_high_score,
+            name_box,
+            game_over_title,
+            final_score,
+            high_scores,
+            buttons,
+            shown: None,
+        }
+    }
+
+    /// Action of the button `button`, if it belongs to the menus.
+    pub fn action(&self, button: Handle<UiNode>) -> Option<Action> {
+        self.buttons
+            .iter()
+            .find(|b| b.button == button)
+            .map(|b| b.action)
+    }
+
+    /// Shows the menu of `state`, if it is not on screen yet. `rank` is the place of the run in
+    /// `high_scores`, if it made the table, and `victory` whether the run ended by completing
+    /// the last level.
+    pub fn show(
+        &mut self,
+        ui: &UserInterface,
+        state: UiState,
+        score: f32,
+        high_scores: &HighScoreTable,
+        rank: Option<usize>,
+        victory: bool,
+    ) {
+        if self.shown == Some(state) {
+            return;
+        }
+        self.shown = Some(state);
+        for (panel, visible) in [
+            (self.main_menu, state == UiState::MainMenu),
+            (self.pause_menu, state == UiState::Paused),
+            (self.level_complete, state == UiState::LevelComplete),
+            (self.name_entry, state == UiState::NameEntry),
+            (self.game_over, state == UiState::GameOver),
+        ] {
+            ui.send_message(WidgetMessage::visibility(
+                panel,
+                MessageDirection::ToWidget,
+                visible,
+            ));
+        }
+        if state == UiState::LevelComplete {
+            ui.send_message(TextMessage::text(
+                self.level_score,
+                MessageDirection::ToWidget,
+                format!("Score: {score}"),
+            ));
+        }
+        if state == UiState::NameEntry {
+            ui.send_message(TextMessage::text(
+                self.new_high_score,
+                MessageDirection::ToWidget,
+                format!("#{} with {score} points", rank.map_or(0, |r| r + 1)),
+            ));
+            ui.send_message(WidgetMessage::focus(
+                self.name_box,
+                MessageDirection::ToWidget,
+            ));
+        }
+        if state == UiState::GameOver {
+            ui.send_message(TextMessage::text(
+                self.game_over_title,
+                MessageDirection::ToWidget,
+                if victory { "Victory!" } else { "Game Over" }.to_owned(),
+            ));
+            ui.send_message(TextMessage::text(
+                self.final_score,
+                MessageDirection::ToWidget,
+                format!("Final score: {score}"),
+            ));
+            ui.send_message(TextMessage::text(
+                self.high_scores,
+                MessageDirection::ToWidget,
+                high_scores.table_text(),
+            ));
+        }
+    }
+}
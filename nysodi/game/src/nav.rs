@@ -0,0 +1,425 @@
+//! Grid pathfinding around static obstacles.
+//!
+//! [`NavGrid`] covers the static colliders of the scene, walls and tile map colliders alike,
+//! with square cells, and finds paths between cells with A*. The [`Game`](crate::Game) builds the
+//! grid once the scene and its tile sets are loaded, and bots ask it for a path when a wall stands
+//! between them and the player. Searches are limited per tick, so dozens of bots stay cheap.
+//! Outside the grid, e.g. in a scene without static colliders, everything is reachable in a
+//! straight line.
+
+use fyrox::{
+    core::{
+        algebra::{Matrix4, Point2, Point3, Vector2, Vector3},
+        pool::Handle,
+    },
+    graph::SceneGraph,
+    scene::{
+        dim2::{
+            collider::{Collider, ColliderShape},
+            rigidbody::RigidBody,
+        },
+        graph::Graph,
+        node::Node,
+        rigidbody::RigidBodyType,
+        tilemap::TileMap,
+    },
+};
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+/// Side of a grid cell, in units.
+pub const CELL_SIZE: f32 = 0.5;
+/// Distance kept between the center of a bot and obstacles, about half the width of a bot.
+pub const CLEARANCE: f32 = 0.3;
+/// Path searches allowed per tick for all bots together. Bots over the budget keep their path
+/// and search again on the next tick.
+pub const SEARCHES_PER_TICK: u32 = 4;
+
+/// Cost of a straight and of a diagonal step between cells.
+const STRAIGHT: u32 = 10;
+const DIAGONAL: u32 = 14;
+
+/// Axis-aligned rectangle covered by an obstacle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Bounds {
+    pub min: Vector2<f32>,
+    pub max: Vector2<f32>,
+}
+
+impl Bounds {
+    pub fn of_points(points: impl IntoIterator<Item = Vector2<f32>>) -> Option<Self> {
+        points.into_iter().fold(None, |bounds, p| {
+            Some(match bounds {
+                None => Bounds { min: p, max: p },
+                Some(Bounds { min, max }) => Bounds {
+                    min: min.inf(&p),
+                    max: max.sup(&p),
+                },
+            })
+        })
+    }
+
+    pub fn union(self, other: Bounds) -> Self {
+        Bounds {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    pub fn inflate(self, by: f32) -> Self {
+        let by = Vector2::repeat(by);
+        Bounds {
+            min: self.min - by,
+            max: self.max + by,
+        }
+    }
+}
+
+/// Walkable and blocked cells of a scene.
+#[derive(Debug, Clone, Default)]
+pub struct NavGrid {
+    /// World position of the corner of the first cell.
+    origin: Vector2<f32>,
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+    /// Whether the grid covers every obstacle of its scene. A grid is rebuilt until the tile
+    /// sets of the scene are loaded.
+    ready: bool,
+    searches_left: u32,
+}
+
+impl NavGrid {
+    /// Covers the static colliders of `graph`, leaving a walkable border around them.
+    pub fn build(graph: &Graph) -> Self {
+        let (obstacles, ready) = static_obstacles(graph);
+        let Some(extent) = obstacles.iter().copied().reduce(Bounds::union) else {
+            return Self {
+                ready,
+                ..Default::default()
+            };
+        };
+        let extent = extent.inflate(CLEARANCE + 2.0 * CELL_SIZE);
+        let size = (extent.max - extent.min) / CELL_SIZE;
+        let mut grid = Self {
+            origin: extent.min,
+            width: size.x.ceil() as usize,
+            height: size.y.ceil() as usize,
+            blocked: Vec::new(),
+            ready,
+            searches_left: SEARCHES_PER_TICK,
+        };
+        grid.blocked = vec![false; grid.width * grid.height];
+        for obstacle in obstacles {
+            let obstacle = obstacle.inflate(CLEARANCE);
+            let (Some(first), Some(last)) = (
+                grid.clamped_cell(obstacle.min),
+                grid.clamped_cell(obstacle.max),
+            ) else {
+                continue;
+            };
+            for y in first.y..=last.y {
+                for x in first.x..=last.x {
+                    let center = grid.center(Vector2::new(x, y));
+                    if (obstacle.min.x..=obstacle.max.x).contains(&center.x)
+                        && (obstacle.min.y..=obstacle.max.y).contains(&center.y)
+                    {
+                        grid.blocked[y * grid.width + x] = true;
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    /// Whether the grid needs to be built again, once the resources of the scene are loaded.
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    /// Refills the search budget. Called once per tick.
+    pub fn new_tick(&mut self) {
+        self.searches_left = SEARCHES_PER_TICK;
+    }
+
+    /// Takes a search from the budget of the tick, if any is left.
+    pub fn try_search(&mut self) -> bool {
+        let allowed = self.searches_left > 0;
+        self.searches_left = self.searches_left.saturating_sub(1);
+        allowed
+    }
+
+    /// Whether the point `position` is too close to an obstacle for a bot to stand there.
+    pub fn is_blocked(&self, position: Vector2<f32>) -> bool {
+        self.cell(position)
+            .is_some_and(|c| self.blocked[self.index(c)])
+    }
+
+    /// Whether a bot can walk in a straight line from `from` to `to`. The cells of both ends
+    /// are ignored, so a bot standing against a wall can still walk away from it.
+    pub fn is_clear(&self, from: Vector2<f32>, to: Vector2<f32>) -> bool {
+        let (start, goal) = (self.cell(from), self.cell(to));
+        let steps = ((to - from).norm() / (CELL_SIZE / 4.0)).ceil() as usize;
+        (1..steps).all(|i| {
+            let point = from.lerp(&to, i as f32 / steps as f32);
+            match self.cell(point) {
+                Some(cell) => {
+                    Some(cell) == start || Some(cell) == goal || !self.blocked[self.index(cell)]
+                }
+                None => true,
+            }
+        })
+    }
+
+    /// Shortest walkable path from `from` to `to`, as the points to walk through, ending with
+    /// `to`. Ends outside of the grid are walked to from its closest cell. `None` when there is
+    /// no path, or no grid at all.
+    pub fn find_path(&self, from: Vector2<f32>, to: Vector2<f32>) -> Option<Vec<Vector2<f32>>> {
+        let start = self.clamped_cell(from)?;
+        let goal = self.clamped_cell(to)?;
+        let (start, goal) = (self.index(start), self.index(goal));
+        let heuristic = |index: usize| {
+            let (dx, dy) = (
+                (index % self.width).abs_diff(goal % self.width) as u32,
+                (index / self.width).abs_diff(goal / self.width) as u32,
+            );
+            STRAIGHT * dx.max(dy) + (DIAGONAL - STRAIGHT) * dx.min(dy)
+        };
+
+        let mut cost = vec![u32::MAX; self.blocked.len()];
+        let mut came_from = vec![usize::MAX; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        cost[start] = 0;
+        open.push(Reverse((heuristic(start), start)));
+        while let Some(Reverse((_, current))) = open.pop() {
+            if current == goal {
+                return Some(self.smooth(from, to, self.cells_to(goal, &came_from)));
+            }
+            for (next, step) in self.neighbours(current, goal) {
+                let next_cost = cost[current] + step;
+                if next_cost < cost[next] {
+                    cost[next] = next_cost;
+                    came_from[next] = current;
+                    open.push(Reverse((next_cost + heuristic(next), next)));
+                }
+            }
+        }
+        None
+    }
+
+    /// Walkable cells around `index` and the cost to step on them. Diagonal steps don't cut
+    /// the corners of obstacles.
+    fn neighbours(&self, index: usize, goal: usize) -> impl Iterator<Item = (usize, u32)> + '_ {
+        let (x, y) = ((index % self.width) as isize, (index / self.width) as isize);
+        let walkable = move |dx: isize, dy: isize| {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 || nx >= self.width as isize || ny >= self.height as isize {
+                return None;
+            }
+            let next = ny as usize * self.width + nx as usize;
+            (next == goal || !self.blocked[next]).then_some(next)
+        };
+        [
+            (-1, 0),
+            (1, 0),
+            (0, -1),
+            (0, 1),
+            (-1, -1),
+            (1, -1),
+            (-1, 1),
+            (1, 1),
+        ]
+        .into_iter()
+        .filter_map(move |(dx, dy)| {
+            let next = walkable(dx, dy)?;
+            if dx != 0 && dy != 0 {
+                walkable(dx, 0)?;
+                walkable(0, dy)?;
+                Some((next, DIAGONAL))
+            } else {
+                Some((next, STRAIGHT))
+            }
+        })
+    }
+
+    /// Centers of the cells from the start to `goal`, the start excluded.
+    fn cells_to(&self, goal: usize, came_from: &[usize]) -> Vec<Vector2<f32>> {
+        let mut cells = Vec::new();
+        let mut current = goal;
+        while came_from[current] != usize::MAX {
+            cells.push(self.center(Vector2::new(current % self.width, current / self.width)));
+            current = came_from[current];
+        }
+        cells.reverse();
+        cells
+    }
+
+    /// Keeps only the points of `path` where it turns around an obstacle, and ends it at `to`.
+    fn smooth(
+        &self,
+        from: Vector2<f32>,
+        to: Vector2<f32>,
+        mut path: Vec<Vector2<f32>>,
+    ) -> Vec<Vector2<f32>> {
+        path.pop();
+        path.push(to);
+        let mut smoothed = Vec::new();
+        let mut position = from;
+        let mut i = 0;
+        while i < path.len() {
+            // Furthest point of the path in sight.
+            let next = (i..path.len())
+                .rev()
+                .find(|&j| self.is_clear(position, path[j]))
+                .unwrap_or(i);
+            position = path[next];
+            smoothed.push(position);
+            i = next + 1;
+        }
+        smoothed
+    }
+
+    fn cell(&self, position: Vector2<f32>) -> Option<Vector2<usize>> {
+        let cell = (position - self.origin) / CELL_SIZE;
+        (cell.x >= 0.0
+            && cell.y >= 0.0
+            && (cell.x as usize) < self.width
+            && (cell.y as usize) < self.height)
+            .then(|| Vector2::new(cell.x as usize, cell.y as usize))
+    }
+
+    /// Cell of `position`, moved inside the grid.
+    fn clamped_cell(&self, position: Vector2<f32>) -> Option<Vector2<usize>> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let cell = (position - self.origin) / CELL_SIZE;
+        Some(Vector2::new(
+            (cell.x.max(0.0) as usize).min(self.width - 1),
+            (cell.y.max(0.0) as usize).min(self.height - 1),
+        ))
+    }
+
+    fn index(&self, cell: Vector2<usize>) -> usize {
+        cell.y * self.width + cell.x
+    }
+
+    fn center(&self, cell: Vector2<usize>) -> Vector2<f32> {
+        self.origin + (cell.cast::<f32>() + Vector2::repeat(0.5)) * CELL_SIZE
+    }
+}
+
+/// Bounds of the static, solid colliders of `graph`, and whether the tile sets of its tile map
+/// colliders are all loaded.
+pub(crate) fn static_obstacles(graph: &Graph) -> (Vec<Bounds>, bool) {
+    let mut ready = true;
+    let mut obstacles = Vec::new();
+    for (handle, node) in graph.pair_iter() {
+        let Some(collider) = node.cast::<Collider>() else {
+            continue;
+        };
+        let is_static = graph
+            .try_get_of_type::<RigidBody>(node.parent())
+            .is_some_and(|body| body.body_type() == RigidBodyType::Static);
+        if !is_static || collider.is_sensor() {
+            continue;
+        }
+        ready &= collider_bounds(graph, handle, collider, &mut obstacles);
+    }
+    (obstacles, ready)
+}
+
+/// Adds the bounds covered by `collider` to `bounds`. Returns `false` if the collider is a
+/// tile map whose tile set is still loading.
+fn collider_bounds(
+    graph: &Graph,
+    handle: Handle<Node>,
+    collider: &Collider,
+    bounds: &mut Vec<Bounds>,
+) -> bool {
+    let transform = graph[handle].global_transform();
+    let point = |p: Vector2<f32>| {
+        transform
+            .transform_point(&Point3::new(p.x, p.y, 0.0))
+            .coords
+            .xy()
+    };
+    let scale = transform.column(0).xyz().norm();
+    let shape = match collider.shape() {
+        ColliderShape::Ball(ball) => {
+            Bounds::of_points([point(Vector2::zeros())]).map(|b| b.inflate(ball.radius * scale))
+        }
+        ColliderShape::Cuboid(cuboid) => {
+            let h = cuboid.half_extents;
+            Bounds::of_points(
+                [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                    .map(|(x, y)| point(Vector2::new(h.x * x, h.y * y))),
+            )
+        }
+        ColliderShape::Capsule(capsule) => {
+            Bounds::of_points([point(capsule.begin), point(capsule.end)])
+                .map(|b| b.inflate(capsule.radius * scale))
+        }
+        ColliderShape::Segment(segment) => {
+            Bounds::of_points([point(segment.begin), point(segment.end)])
+        }
+        ColliderShape::Triangle(triangle) => {
+            Bounds::of_points([triangle.a, triangle.b, triangle.c].map(point))
+        }
+        ColliderShape::TileMap(shape) => {
+            return tile_map_bounds(graph, shape.tile_map.0, &shape.layer_name, bounds);
+        }
+        // Meshes and height fields aren't used for the walls of the arena.
+        ColliderShape::Trimesh(_) | ColliderShape::Heightfield(_) => None,
+    };
+    bounds.extend(shape);
+    true
+}
+
+/// Adds the bounds of every tile of `tile_map` with a collider on the layer `layer_name`, the
+/// way the physics builds the collider of the tile map. Returns `false` if its tile set is
+/// still loading.
+fn tile_map_bounds(
+    graph: &Graph,
+    tile_map: Handle<Node>,
+    layer_name: &str,
+    bounds: &mut Vec<Bounds>,
+) -> bool {
+    let Some(tile_map) = graph.try_get_of_type::<TileMap>(tile_map) else {
+        return true;
+    };
+    let (Some(tile_set), Some(tiles)) = (tile_map.tile_set(), tile_map.tiles()) else {
+        return true;
+    };
+    if tile_set.is_loading() || tiles.is_loading() {
+        return false;
+    }
+    let tile_set = tile_set.data_ref();
+    let tiles = tiles.data_ref();
+    let (Some(tile_set), Some(tiles)) = (tile_set.as_loaded_ref(), tiles.as_loaded_ref()) else {
+        // Failed to load, nothing to wait for.
+        return true;
+    };
+    let Some(layer) = tile_set.collider_name_to_uuid(&layer_name.into()) else {
+        return true;
+    };
+    let scale = tile_map.tile_scale();
+    let transform: Matrix4<f32> = tile_map.global_transform()
+        * Matrix4::new_nonuniform_scaling(&Vector3::new(-scale.x, scale.y, 1.0));
+    for (position, handle) in tiles.iter() {
+        let Some(collider) = tile_set
+            .get_tile_data(handle.into())
+            .and_then(|tile| tile.colliders.get(&layer))
+        else {
+            continue;
+        };
+        let mut vertices: Vec<Point2<f32>> = Vec::new();
+        collider.build_collider_shape(
+            &transform,
+            position.cast::<f32>().to_homogeneous(),
+            &mut vertices,
+            &mut Vec::new(),
+        );
+        bounds.extend(Bounds::of_points(vertices.into_iter().map(|v| v.coords)));
+    }
+    true
+}
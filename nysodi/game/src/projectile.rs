@@ -0,0 +1,225 @@
+//! Projectiles fired by ranged bots and by the player's ranged weapon.
+//!
+//! A projectile is a rigid body with a sensor collider and a sprite, flying in a straight line
+//! with the [`Projectile`] script. It hits the first node of the opposing [`Faction`] it
+//! touches, never its owner, and is removed on a hit, when it runs into a static body or when
+//! its lifetime is over. Hits are published as [`DamageDealt`] events, like melee hits.
+
+use crate::{
+    bot::Bot,
+    context::TickContext,
+    event::{DamageDealt, ProjectileFired},
+    health::DamageType,
+};
+use fyrox::{
+    core::{
+        algebra::{Vector2, Vector3},
+        pool::Handle,
+        reflect::prelude::*,
+        type_traits::prelude::*,
+        visitor::prelude::*,
+    },
+    graph::{BaseSceneGraph, SceneGraph},
+    gui::texture::Texture,
+    scene::{
+        base::BaseBuilder,
+        dim2::{
+            collider::{Collider, ColliderBuilder, ColliderShape},
+            rectangle::{Rectangle, RectangleBuilder},
+            rigidbody::{RigidBody, RigidBodyBuilder},
+        },
+        graph::Graph,
+        node::Node,
+        rigidbody::RigidBodyType,
+        transform::TransformBuilder,
+    },
+    script::{ScriptContext, ScriptTrait},
+};
+use serde::Deserialize;
+
+/// Side a projectile was fired by; it only hits the other side.
+#[derive(Visit, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Faction {
+    /// Fired by the player, hits bots.
+    #[default]
+    Player,
+    /// Fired by a bot, hits the player.
+    Enemy,
+}
+
+/// How a kind of projectile flies and looks. The damage is set by whoever fires it.
+#[derive(Deserialize, Visit, Reflect, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ProjectileSpec {
+    /// Units per second.
+    pub speed: f32,
+    /// Seconds before a projectile that hit nothing disappears.
+    pub lifetime: f32,
+    /// Distance from the center of a target at which the projectile hits it.
+    pub radius: f32,
+    pub texture: String,
+    pub scale: f32,
+}
+
+impl Default for ProjectileSpec {
+    fn default() -> Self {
+        Self {
+            speed: 6.0,
+            lifetime: 2.0,
+            radius: 0.5,
+            texture: "data/fire.png".to_owned(),
+            scale: 0.35,
+        }
+    }
+}
+
+#[derive(Visit, Reflect, Debug, Clone, Default, TypeUuidProvider, ComponentProvider)]
+#[type_uuid(id = "234abe22-df86-48e4-909e-7fa54419158d")]
+#[visit(optional)]
+pub struct Projectile {
+    pub velocity: Vector2<f32>,
+    pub damage: f32,
+    /// Seconds left before the projectile disappears.
+    pub lifetime: f32,
+    pub radius: f32,
+    /// Node that fired the projectile, never hit by it.
+    pub owner: Handle<Node>,
+    pub faction: Faction,
+}
+
+impl Projectile {
+    pub fn update(&mut self, ctx: &mut TickContext) {
+        self.lifetime -= ctx.dt;
+        if self.lifetime <= 0.0 || hits_static_body(&ctx.scene.graph, ctx.handle) {
+            ctx.scene.graph.remove_node(ctx.handle);
+            return;
+        }
+        // The velocity is set every tick, so a body woken up or pushed keeps its course.
+        if let Some(rb) = ctx.scene.graph.try_get_mut_of_type::<RigidBody>(ctx.handle) {
+            rb.set_lin_vel(self.velocity);
+        }
+
+        let Some(target) = self.find_target(ctx) else {
+            return;
+        };
+        ctx.game.events.publish(DamageDealt {
+            target,
+            source: self.owner,
+            amount: self.damage,
+            kind: DamageType::Physical,
+        });
+        println!("▶ {} hit by a projectile!", ctx.scene.graph[target].name());
+        ctx.scene.graph.remove_node(ctx.handle);
+    }
+
+    /// Closest node of the opposing faction within reach, if any.
+    fn find_target(&self, ctx: &TickContext) -> Option<Handle<Node>> {
+        let graph = &ctx.scene.graph;
+        let position = graph[ctx.handle].global_position().xy();
+        let in_reach = |handle: Handle<Node>| {
+            graph.try_get(handle).and_then(|node| {
+                let distance = (node.global_position().xy() - position).norm();
+                (handle != self.owner && node.visibility() && distance <= self.radius)
+                    .then_some((handle, distance))
+            })
+        };
+        let candidates: Vec<_> = match self.faction {
+            Faction::Player => graph
+                .pair_iter()
+                .filter(|(_, node)| node.try_get_script::<Bot>().is_some())
+                .filter_map(|(handle, _)| in_reach(handle))
+                .collect(),
+            Faction::Enemy => in_reach(ctx.game.player).into_iter().collect(),
+        };
+        candidates
+            .into_iter()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(handle, _)| handle)
+    }
+}
+
+/// Whether the sensor collider of `projectile` touches a static body, e.g. a wall.
+fn hits_static_body(graph: &Graph, projectile: Handle<Node>) -> bool {
+    let Some(sensor) = graph[projectile]
+        .children()
+        .iter()
+        .find_map(|&child| graph.try_get_of_type::<Collider>(child))
+    else {
+        return false;
+    };
+    sensor
+        .intersects(&graph.physics2d)
+        .filter(|i| i.has_any_active_contact)
+        .flat_map(|i| [i.collider1, i.collider2])
+        .filter_map(|collider| graph.try_get_of_type::<Collider>(collider))
+        .filter_map(|collider| graph.try_get_of_type::<RigidBody>(collider.parent()))
+        .any(|body| body.body_type() == RigidBodyType::Static)
+}
+
+/// Fires a projectile of `spec` from `position` towards `direction`, on behalf of `owner`.
+pub fn fire(
+    spec: &ProjectileSpec,
+    damage: f32,
+    faction: Faction,
+    owner: Handle<Node>,
+    position: Vector2<f32>,
+    direction: Vector2<f32>,
+    ctx: &mut TickContext,
+) -> Handle<Node> {
+    let velocity = direction.try_normalize(f32::EPSILON).unwrap_or_default() * spec.speed;
+    let texture = ctx.resource_manager.request::<Texture>(&spec.texture);
+    let graph = &mut ctx.scene.graph;
+    let sprite = RectangleBuilder::new(
+        BaseBuilder::new().with_local_transform(
+            TransformBuilder::new()
+                .with_local_scale(Vector3::new(spec.scale, spec.scale, 1.0))
+                .build(),
+        ),
+    )
+    .build(graph);
+    if let Some(rect) = graph.try_get_mut_of_type::<Rectangle>(sprite) {
+        rect.material().data_ref().bind("diffuseTexture", texture);
+    }
+    let sensor = ColliderBuilder::new(BaseBuilder::new())
+        .with_shape(ColliderShape::ball(spec.scale / 2.0))
+        .with_sensor(true)
+        .build(graph);
+    let projectile = RigidBodyBuilder::new(
+        BaseBuilder::new()
+            .with_name("Projectile")
+            .with_children(&[sprite, sensor])
+            .with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(position.x, position.y, 0.0))
+                    .build(),
+            )
+            .with_script(Projectile {
+                velocity,
+                damage,
+                lifetime: spec.lifetime,
+                radius: spec.radius,
+                owner,
+                faction,
+            }),
+    )
+    .with_body_type(RigidBodyType::Dynamic)
+    .with_lin_vel(velocity)
+    .with_gravity_scale(0.0)
+    .with_rotation_locked(true)
+    .with_can_sleep(false)
+    .build(graph);
+
+    ctx.game
+        .events
+        .publish(ProjectileFired { projectile, owner });
+    projectile
+}
+
+impl ScriptTrait for Projectile {
+    fn on_update(&mut self, ctx: &mut ScriptContext) {
+        let mut ctx = TickContext::from_script(ctx);
+        if ctx.game.is_running() {
+            self.update(&mut ctx);
+        }
+    }
+}
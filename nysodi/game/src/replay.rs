@@ -0,0 +1,200 @@
+//! Recording and replay of gameplay sessions.
+//!
+//! Input actions reach the scripts through the [`InputStream`] owned by the
+//! [`Game`](crate::Game) plugin, one batch per frame. While recording, every batch is stored in
+//! a [`Recording`] together with the RNG seed and the time step, so feeding the batches back on
+//! the same frames reproduces the session exactly.
+//!
+//! The executor starts recording when `NYSODI_RECORD` holds a file path and replays a recording
+//! when `NYSODI_REPLAY` does.
+
+use crate::config::{self, ConfigError};
+use crate::input::Action;
+use fyrox::core::log::Log;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding the path a new recording is written to.
+pub const RECORD_VAR: &str = "NYSODI_RECORD";
+/// Environment variable holding the path of a recording to replay.
+pub const REPLAY_VAR: &str = "NYSODI_REPLAY";
+
+/// Action presses and releases of a single frame.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FrameInput {
+    pub frame: u64,
+    pub actions: Vec<(Action, bool)>,
+}
+
+/// A recorded session.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Recording {
+    /// Seed of the game RNG during the session.
+    pub seed: u64,
+    /// Time step of every frame.
+    pub dt: f32,
+    /// Length of the session in frames.
+    pub frames: u64,
+    /// Input of the frames that had any, in order.
+    pub input: Vec<FrameInput>,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        config::load(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        config::save(path, self)
+    }
+}
+
+#[derive(Debug, Default)]
+enum InputMode {
+    #[default]
+    Live,
+    Recording {
+        recording: Recording,
+        path: Option<PathBuf>,
+    },
+    Replaying {
+        recording: Recording,
+        cursor: usize,
+    },
+}
+
+/// Source of the input actions handed to the scripts each frame.
+#[derive(Debug, Default)]
+pub struct InputStream {
+    mode: InputMode,
+    frame: u64,
+    pending: Vec<(Action, bool)>,
+}
+
+impl InputStream {
+    /// Records live input of a session played with the given RNG seed. The recording is written
+    /// to `path`, if any, by [`InputStream::finish`].
+    pub fn recording(seed: u64, path: Option<PathBuf>) -> Self {
+        Self {
+            mode: InputMode::Recording {
+                recording: Recording {
+                    seed,
+                    ..Default::default()
+                },
+                path,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Feeds back the input of `recording`, ignoring live input until it ends.
+    pub fn replaying(recording: Recording) -> Self {
+        Self {
+            mode: InputMode::Replaying {
+                recording,
+                cursor: 0,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Picks the mode requested through [`RECORD_VAR`] or [`REPLAY_VAR`].
+    pub fn from_env() -> Self {
+        if let Ok(path) = std::env::var(REPLAY_VAR) {
+            match Recording::load(&path) {
+                Ok(recording) => {
+                    Log::info(format!("Replaying {path}"));
+                    return Self::replaying(recording);
+                }
+                Err(err) => Log::err(format!("{path}: {err}")),
+            }
+        }
+        if let Ok(path) = std::env::var(RECORD_VAR) {
+            Log::info(format!("Recording input to {path}"));
+            return Self::recording(0, Some(path.into()));
+        }
+        Self::default()
+    }
+
+    /// Seed of the recording being replayed.
+    pub fn replay_seed(&self) -> Option<u64> {
+        match &self.mode {
+            InputMode::Replaying { recording, .. } => Some(recording.seed),
+            _ => None,
+        }
+    }
+
+    /// Sets the RNG seed stored in the recording being made, if any.
+    pub fn set_seed(&mut self, seed: u64) {
+        if let InputMode::Recording { recording, .. } = &mut self.mode {
+            recording.seed = seed;
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self.mode, InputMode::Replaying { .. })
+    }
+
+    /// Queues live input for the next frame. Ignored while replaying.
+    pub fn push(&mut self, action: Action, pressed: bool) {
+        if !self.is_replaying() {
+            self.pending.push((action, pressed));
+        }
+    }
+
+    /// Returns the input of the current frame and moves on to the next one.
+    pub fn next_frame(&mut self, dt: f32) -> Vec<(Action, bool)> {
+        let frame = self.frame;
+        self.frame += 1;
+        let actions = std::mem::take(&mut self.pending);
+
+        match &mut self.mode {
+            InputMode::Live => actions,
+            InputMode::Recording { recording, .. } => {
+                if recording.frames == 0 {
+                    recording.dt = dt;
+                }
+                recording.frames = frame + 1;
+                if !actions.is_empty() {
+                    recording.input.push(FrameInput {
+                        frame,
+                        actions: actions.clone(),
+                    });
+                }
+                actions
+            }
+            InputMode::Replaying { recording, cursor } => {
+                if frame == 0 && recording.dt != dt {
+                    Log::warn(format!(
+                        "Replay recorded with a time step of {}s is played at {}s",
+                        recording.dt, dt
+                    ));
+                }
+                let mut actions = Vec::new();
+                while let Some(input) = recording.input.get(*cursor).filter(|i| i.frame <= frame) {
+                    actions.extend_from_slice(&input.actions);
+                    *cursor += 1;
+                }
+                if frame + 1 >= recording.frames {
+                    println!("▶ Replay finished, input is live again");
+                    self.mode = InputMode::Live;
+                }
+                actions
+            }
+        }
+    }
+
+    /// Stops recording and returns the session, writing it to disk if a path was given.
+    pub fn finish(&mut self) -> Option<Recording> {
+        let InputMode::Recording { recording, path } = std::mem::take(&mut self.mode) else {
+            return None;
+        };
+        if let Some(path) = path {
+            match recording.save(&path) {
+                Ok(()) => Log::info(format!("Recording saved to {}", path.display())),
+                Err(err) => Log::err(format!("{}: {err}", path.display())),
+            }
+        }
+        Some(recording)
+    }
+}
@@ -0,0 +1,95 @@
+//! Seeded random number generator shared by every gameplay system.
+//!
+//! The [`Game`](crate::Game) plugin owns a single [`GameRng`]. Spawns and AI decisions draw from
+//! it instead of `thread_rng`, so a run can be reproduced from its seed. The seed is picked at
+//! startup from the `NYSODI_SEED` environment variable, or from the clock if it is not set, and
+//! is written to the log.
+
+use fyrox::{
+    core::visitor::prelude::*,
+    rand::{rngs::StdRng, Error, RngCore, SeedableRng},
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable holding the seed of the run.
+pub const SEED_VAR: &str = "NYSODI_SEED";
+
+#[derive(Debug, Clone)]
+pub struct GameRng {
+    seed: u64,
+    /// Seed of the generator since the last [`GameRng::checkpoint`].
+    stream: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            stream: seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Seed the generator was created with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Reseeds the generator from itself, so its state fits in the seed it saves. Saving right
+    /// after a checkpoint lets a loaded game draw the same numbers as the one that kept going.
+    pub fn checkpoint(&mut self) {
+        self.stream = self.rng.next_u64();
+        self.rng = StdRng::seed_from_u64(self.stream);
+    }
+}
+
+/// Stores the state of the last [`GameRng::checkpoint`].
+impl Visit for GameRng {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        let mut region = visitor.enter_region(name)?;
+        self.seed.visit("Seed", &mut region)?;
+        self.stream.visit("Stream", &mut region)?;
+        if region.is_reading() {
+            self.rng = StdRng::seed_from_u64(self.stream);
+        }
+        Ok(())
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+/// Seed for a new run: the value of [`SEED_VAR`] if it holds a number, the current time otherwise.
+pub fn startup_seed() -> u64 {
+    std::env::var(SEED_VAR)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default()
+        })
+}
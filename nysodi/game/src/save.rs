@@ -0,0 +1,96 @@
+//! Saving and loading of a run in progress.
+//!
+//! A save slot is a single binary file holding the scene graph, with the state of every script
+//! (health, timers, active item effects, respawning bots, ...), and the part of the [`Game`]
+//! plugin that belongs to the run: player handle, score and statistics, level and progress
+//! towards its goal, wave director and RNG. Node handles survive the round trip, so scripts and
+//! plugin keep pointing at the same nodes.
+//!
+//! Fields that are not saved are rebuilt after loading: the scripts are started again (the
+//! player rebuilds its animations, bots look their target up from [`Game::player`] every
+//! tick), the waves and items of the level are picked again, the wave director gets its prefab
+//! and archetypes back and pending events are dropped.
+
+use crate::{menu::UiState, nav::NavGrid, Game};
+use fyrox::{
+    asset::manager::ResourceManager,
+    core::{futures::executor::block_on, visitor::prelude::*},
+    engine::SerializationContext,
+    scene::{Scene, SceneLoader},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Slot written and read by the quick-save and quick-load actions.
+pub const QUICK_SLOT: &str = "quick";
+/// Slot written every time a wave is cleared.
+pub const AUTO_SLOT: &str = "autosave";
+/// Directory of the save slots, relative to the working directory of the game.
+pub const SAVE_DIR: &str = "saves";
+
+/// File of the slot `slot` in `dir`.
+pub fn slot_path(dir: &Path, slot: &str) -> PathBuf {
+    dir.join(slot).with_extension("save")
+}
+
+/// Fields of the game that belong to the run.
+fn visit_run(game: &mut Game, visitor: &mut Visitor) -> VisitResult {
+    let mut region = visitor.enter_region("Game")?;
+    game.player.visit("Player", &mut region)?;
+    game.total_score.visit("TotalScore", &mut region)?;
+    game.bot_kill_count.visit("BotKillCount", &mut region)?;
+    game.total_kills.visit("TotalKills", &mut region)?;
+    game.run_time.visit("RunTime", &mut region)?;
+    game.items_collected.visit("ItemsCollected", &mut region)?;
+    game.level.visit("Level", &mut region)?;
+    game.level_progress.visit("LevelProgress", &mut region)?;
+    game.waves.visit("Waves", &mut region)?;
+    game.rng.visit("Rng", &mut region)
+}
+
+/// Writes `scene` and the run state of `game` to `path`.
+pub fn save_run(path: &Path, scene: &mut Scene, game: &mut Game) -> VisitResult {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    game.rng.checkpoint();
+    let mut visitor = Visitor::new();
+    scene.save("Scene", &mut visitor)?;
+    visit_run(game, &mut visitor)?;
+    visitor.save_binary(path)
+}
+
+/// Reads the run saved at `path`: restores the run state of `game` and returns the scene, which
+/// replaces the current one.
+pub fn load_run(
+    path: &Path,
+    serialization_context: Arc<SerializationContext>,
+    resource_manager: &ResourceManager,
+    game: &mut Game,
+) -> Result<Scene, VisitError> {
+    let data = fs::read(path)?;
+    let mut visitor = Visitor::load_from_memory(&data)?;
+    let loader = SceneLoader::load(
+        "Scene",
+        serialization_context,
+        resource_manager.clone(),
+        &mut visitor,
+        None,
+    )?;
+    visit_run(game, &mut visitor)?;
+    let scene = block_on(loader.finish());
+
+    game.events.clear();
+    game.apply_level();
+    game.nav = NavGrid::default();
+    game.flock.update(&scene.graph, game.player);
+    game.waves
+        .load_assets(&game.wave_config, &game.enemies, resource_manager);
+    game.load_requested = None;
+    game.restart_requested = false;
+    game.state = UiState::Playing;
+    Ok(scene)
+}
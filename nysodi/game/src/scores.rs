@@ -0,0 +1,132 @@
+//! Local high-score table.
+//!
+//! The best runs are kept in `highscores.ron`, next to `settings.ron`, with the statistics of
+//! each run, so players can compare runs without screenshots of the terminal. A run that makes
+//! the table is added when the player is defeated, under [`DEFAULT_NAME`] until the player
+//! types a name on the entry screen.
+
+use crate::config::{self, ConfigError};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Location of the high-score table, relative to the working directory of the game.
+pub const HIGH_SCORES_PATH: &str = "highscores.ron";
+/// Number of runs kept in the table.
+pub const MAX_ENTRIES: usize = 10;
+/// Name of a run until the player enters one.
+pub const DEFAULT_NAME: &str = "Player";
+
+/// Statistics of a run, gathered by the game while it is played.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct RunStats {
+    pub score: f32,
+    pub kills: u32,
+    /// Seconds played, pauses excluded.
+    pub survival_time: f32,
+    pub items_collected: u32,
+}
+
+/// A run of the table.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct HighScore {
+    pub name: String,
+    pub stats: RunStats,
+    /// Day the run was played, as `YYYY-MM-DD`.
+    pub date: String,
+}
+
+/// Best runs, highest score first.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct HighScoreTable {
+    pub entries: Vec<HighScore>,
+}
+
+impl HighScoreTable {
+    /// Reads the table from a RON file. A missing file is an empty table.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        if !path.as_ref().exists() {
+            return Ok(Self::default());
+        }
+        config::load(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        config::save(path, self)
+    }
+
+    /// Position a run scoring `score` would take in the table, if it makes it. Ties go below
+    /// the runs already in the table.
+    pub fn rank(&self, score: f32) -> Option<usize> {
+        let rank = self
+            .entries
+            .iter()
+            .take_while(|e| e.stats.score >= score)
+            .count();
+        (rank < MAX_ENTRIES).then_some(rank)
+    }
+
+    /// Adds `entry` at its rank, dropping the runs pushed out of the table. Returns the rank,
+    /// or `None` if the run didn't make the table.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        let rank = self.rank(entry.stats.score)?;
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+
+    /// One line per run: rank, name, score, kills, survival time, items and date.
+    pub fn table_text(&self) -> String {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(rank, e)| {
+                format!(
+                    "{:>2}. {:<12} {:>6}  {:>3} kills  {}  {:>2} items  {}",
+                    rank + 1,
+                    e.name,
+                    e.stats.score,
+                    e.stats.kills,
+                    survival_text(e.stats.survival_time),
+                    e.stats.items_collected,
+                    e.date
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// `seconds` as `m:ss`.
+pub fn survival_text(seconds: f32) -> String {
+    let seconds = seconds.max(0.0) as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Date `days` days after 1970-01-01, as `YYYY-MM-DD`.
+pub fn date(days: i64) -> String {
+    // Civil-from-days conversion of the proleptic Gregorian calendar, by eras of 400 years.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Today's date (UTC), as `YYYY-MM-DD`.
+pub fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    date((seconds / 86_400) as i64)
+}
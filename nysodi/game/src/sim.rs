@@ -0,0 +1,364 @@
+//! Headless simulation of the game.
+//!
+//! [`Simulation`] runs the [`Game`] plugin together with the [`Player`], [`Bot`] and
+//! [`Projectile`] scripts with a fixed time step, without a window, a renderer or the engine's
+//! script runtime. Tests build a scene (or load one), inject input and assert on the scene
+//! graph.
+
+use crate::{
+    bot::Bot,
+    context::{self, run_script},
+    input::Action,
+    projectile::Projectile,
+    register_scripts,
+    replay::{InputStream, Recording},
+    rng::GameRng,
+    save, Game, Player,
+};
+use fyrox::{
+    asset::{io::FsResourceIo, manager::ResourceManager},
+    core::{
+        algebra::{Vector2, Vector3},
+        futures::executor::block_on,
+        log::Log,
+        pool::Handle,
+        task::TaskPool,
+        visitor::VisitError,
+    },
+    engine::{Engine, EngineInitParams, SerializationContext},
+    graph::{BaseSceneGraph, SceneGraph},
+    gui::constructor::new_widget_constructor_container,
+    keyboard::KeyCode,
+    scene::{
+        base::BaseBuilder,
+        dim2::{
+            collider::{ColliderBuilder, ColliderShape},
+            rectangle::RectangleBuilder,
+            rigidbody::RigidBodyBuilder,
+        },
+        node::Node,
+        rigidbody::RigidBodyType,
+        transform::TransformBuilder,
+        Scene, SceneLoader,
+    },
+    script::ScriptTrait,
+};
+use std::{collections::HashSet, path::Path, sync::Arc};
+
+pub struct Simulation {
+    /// Engine instance used only for its resource manager and serialization context; it never
+    /// creates a window or a graphics context.
+    pub engine: Engine,
+    pub scene: Scene,
+    pub game: Game,
+    /// Fixed time step of a single tick.
+    pub dt: f32,
+    /// Number of ticks run so far.
+    pub ticks: u64,
+    started: HashSet<Handle<Node>>,
+    /// Copy of the scene taken on the first tick, restored on restart.
+    initial_scene: Option<Scene>,
+}
+
+impl Simulation {
+    pub const DEFAULT_DT: f32 = 1.0 / 60.0;
+
+    /// Creates a simulation with an empty scene.
+    pub fn new(game: Game) -> Self {
+        let task_pool = Arc::new(TaskPool::new());
+        let serialization_context = Arc::new(SerializationContext::new());
+        register_scripts(&serialization_context.script_constructors);
+        let engine = Engine::new(EngineInitParams {
+            graphics_context_params: Default::default(),
+            serialization_context,
+            widget_constructors: Arc::new(new_widget_constructor_container()),
+            resource_manager: ResourceManager::new(task_pool.clone()),
+            task_pool,
+        })
+        .expect("headless engine must initialize");
+
+        let mut sim = Self {
+            engine,
+            scene: Scene::new(),
+            game,
+            dt: Self::DEFAULT_DT,
+            ticks: 0,
+            started: Default::default(),
+            initial_scene: None,
+        };
+        sim.scene
+            .graph
+            .physics2d
+            .gravity
+            .set_value_and_mark_modified(Vector2::zeros());
+        sim.game
+            .prepare_scene(&mut sim.scene, &sim.engine.resource_manager);
+        sim
+    }
+
+    /// Creates a simulation running the scene stored at `path`.
+    pub fn load_scene(game: Game, path: impl AsRef<Path>) -> Result<Self, VisitError> {
+        let mut sim = Self::new(game);
+        sim.scene = sim.read_scene(path)?;
+        sim.game
+            .prepare_scene(&mut sim.scene, &sim.engine.resource_manager);
+        Ok(sim)
+    }
+
+    fn read_scene(&self, path: impl AsRef<Path>) -> Result<Scene, VisitError> {
+        let (loader, _) = block_on(SceneLoader::from_file(
+            path,
+            &FsResourceIo,
+            self.engine.serialization_context.clone(),
+            self.engine.resource_manager.clone(),
+        ))?;
+        Ok(block_on(loader.finish()))
+    }
+
+    /// Adds a player rigid body with a sprite and a collider at `position`.
+    pub fn add_player(&mut self, position: Vector2<f32>) -> Handle<Node> {
+        let graph = &mut self.scene.graph;
+        let sprite = RectangleBuilder::new(BaseBuilder::new()).build(graph);
+        let collider = ColliderBuilder::new(BaseBuilder::new())
+            .with_shape(ColliderShape::cuboid(0.25, 0.4))
+            .build(graph);
+        RigidBodyBuilder::new(
+            BaseBuilder::new()
+                .with_name("Player")
+                .with_children(&[sprite, collider])
+                .with_local_transform(
+                    TransformBuilder::new()
+                        .with_local_position(Vector3::new(position.x, position.y, 0.0))
+                        .build(),
+                )
+                .with_script(Player {
+                    sprite,
+                    ..Default::default()
+                }),
+        )
+        .with_rotation_locked(true)
+        .with_gravity_scale(0.0)
+        .build(graph)
+    }
+
+    /// Adds a bot rigid body with a collider at `position`.
+    pub fn add_bot(&mut self, position: Vector2<f32>) -> Handle<Node> {
+        let graph = &mut self.scene.graph;
+        let collider = ColliderBuilder::new(BaseBuilder::new())
+            .with_shape(ColliderShape::cuboid(0.25, 0.4))
+            .build(graph);
+        RigidBodyBuilder::new(
+            BaseBuilder::new()
+                .with_name("Skeleton")
+                .with_children(&[collider])
+                .with_local_transform(
+                    TransformBuilder::new()
+                        .with_local_position(Vector3::new(position.x, position.y, 0.0))
+                        .build(),
+                )
+                .with_script(Bot::default()),
+        )
+        .with_body_type(RigidBodyType::Dynamic)
+        .with_rotation_locked(true)
+        .with_gravity_scale(0.0)
+        .build(graph)
+    }
+
+    /// Queues an action press or release, the scripts receive it on the next tick.
+    pub fn action(&mut self, action: Action, pressed: bool) {
+        self.game.input.push(action, pressed);
+    }
+
+    /// Queues the actions bound to a key, as if it was pressed or released on the keyboard.
+    pub fn key(&mut self, key: KeyCode, pressed: bool) {
+        let bindings = self.game.bindings.clone();
+        for action in bindings.key_actions(key) {
+            self.action(action, pressed);
+        }
+    }
+
+    /// Presses and immediately releases a key.
+    pub fn tap(&mut self, key: KeyCode) {
+        self.key(key, true);
+        self.key(key, false);
+    }
+
+    /// Runs a single tick: physics, the game plugin, then the scripts.
+    pub fn step(&mut self) {
+        if self.initial_scene.is_none() {
+            self.initial_scene = Some(self.scene.clone_one_to_one().0);
+        }
+        let dt = self.dt;
+        self.engine.resource_manager.state().update(dt);
+        self.scene
+            .update(Vector2::new(1.0, 1.0), dt, Default::default());
+        self.game
+            .update_scene(&mut self.scene, &self.engine.resource_manager, dt);
+        if self.game.restart_requested {
+            self.restart();
+        }
+        if self.game.next_level_requested {
+            self.next_level();
+        }
+        if let Some(path) = self.game.load_requested.take() {
+            if let Err(err) = self.load_run(&path) {
+                Log::err(format!("{}: {err}", path.display()));
+            }
+        }
+        let rm = &self.engine.resource_manager;
+
+        // Like the engine, every new script is started before any script is updated.
+        let nodes = context::script_nodes(&self.scene);
+        for &handle in &nodes {
+            if self.started.insert(handle) {
+                run_script::<Player>(&mut self.scene, &mut self.game, rm, dt, handle, |s, ctx| {
+                    s.start(ctx)
+                });
+                run_script::<Bot>(&mut self.scene, &mut self.game, rm, dt, handle, |s, ctx| {
+                    s.start(ctx)
+                });
+            }
+        }
+        // Scripts are frozen in the menus, like in the engine.
+        if self.game.is_running() {
+            for handle in nodes {
+                run_script::<Player>(&mut self.scene, &mut self.game, rm, dt, handle, |s, ctx| {
+                    s.update(ctx)
+                });
+                run_script::<Bot>(&mut self.scene, &mut self.game, rm, dt, handle, |s, ctx| {
+                    s.update(ctx)
+                });
+                run_script::<Projectile>(
+                    &mut self.scene,
+                    &mut self.game,
+                    rm,
+                    dt,
+                    handle,
+                    |s, ctx| s.update(ctx),
+                );
+            }
+        }
+        self.started
+            .retain(|h| self.scene.graph.is_valid_handle(*h));
+        // Nodes spawned or moved by scripts get their global transforms now rather than on the
+        // next tick, so tests observe them right away.
+        self.scene.graph.update_hierarchical_data();
+
+        self.ticks += 1;
+    }
+
+    /// Starts a new run on a copy of the scene as it was on the first tick, like the game
+    /// reloading its scene. Nodes of the new scene may have other handles than the old ones.
+    pub fn restart(&mut self) {
+        let Some(initial) = &self.initial_scene else {
+            return;
+        };
+        self.scene = initial.clone_one_to_one().0;
+        self.started.clear();
+        self.game.reset_run();
+        self.game
+            .prepare_scene(&mut self.scene, &self.engine.resource_manager);
+    }
+
+    /// Moves on to the next level and loads its scene, like the game does once a level is
+    /// complete.
+    pub fn next_level(&mut self) {
+        self.game.next_level();
+        match self.read_scene(&self.game.scene_path) {
+            Ok(scene) => {
+                self.scene = scene;
+                self.started.clear();
+                self.game
+                    .prepare_scene(&mut self.scene, &self.engine.resource_manager);
+            }
+            Err(err) => Log::err(format!("{}: {err}", self.game.scene_path.display())),
+        }
+    }
+
+    /// Replaces the scene and the run state with the run saved at `path`.
+    pub fn load_run(&mut self, path: &Path) -> Result<(), VisitError> {
+        let serialization_context = self.engine.serialization_context.clone();
+        let rm = &self.engine.resource_manager;
+        self.scene = save::load_run(path, serialization_context, rm, &mut self.game)?;
+        self.started.clear();
+        Ok(())
+    }
+
+    /// Runs `ticks` ticks.
+    pub fn run(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    /// Records the input fed from now on, with the seed the game RNG is reset to.
+    pub fn start_recording(&mut self, seed: u64) {
+        self.game.rng = GameRng::new(seed);
+        self.game.input = InputStream::recording(seed, None);
+    }
+
+    /// Stops recording and returns the recorded session.
+    pub fn finish_recording(&mut self) -> Option<Recording> {
+        self.game.input.finish()
+    }
+
+    /// Plays `recording` from start to end with its seed and time step.
+    pub fn replay(&mut self, recording: Recording) {
+        self.dt = recording.dt;
+        self.game.rng = GameRng::new(recording.seed);
+        let frames = recording.frames;
+        self.game.input = InputStream::replaying(recording);
+        for _ in 0..frames {
+            self.step();
+        }
+    }
+
+    /// Runs for `seconds` of game time.
+    pub fn run_for(&mut self, seconds: f32) {
+        self.run((seconds / self.dt).round() as u32);
+    }
+
+    pub fn player(&self) -> Option<&Player> {
+        self.script(self.game.player)
+    }
+
+    pub fn player_mut(&mut self) -> Option<&mut Player> {
+        self.script_mut(self.game.player)
+    }
+
+    pub fn script<S: ScriptTrait>(&self, handle: Handle<Node>) -> Option<&S> {
+        self.scene.graph.try_get(handle)?.try_get_script::<S>()
+    }
+
+    pub fn script_mut<S: ScriptTrait>(&mut self, handle: Handle<Node>) -> Option<&mut S> {
+        self.scene
+            .graph
+            .try_get_mut(handle)?
+            .try_get_script_mut::<S>()
+    }
+
+    /// Handles of every node carrying a bot script.
+    pub fn bots(&self) -> Vec<Handle<Node>> {
+        self.scene
+            .graph
+            .pair_iter()
+            .filter(|(_, n)| n.try_get_script::<Bot>().is_some())
+            .map(|(h, _)| h)
+            .collect()
+    }
+
+    /// Global 2D position of a node.
+    pub fn position(&self, handle: Handle<Node>) -> Vector2<f32> {
+        self.scene.graph[handle].global_position().xy()
+    }
+
+    /// Handles of every visible node with the given name.
+    pub fn find_visible(&self, name: &str) -> Vec<Handle<Node>> {
+        self.scene
+            .graph
+            .pair_iter()
+            .filter(|(_, n)| n.name() == name && n.visibility())
+            .map(|(h, _)| h)
+            .collect()
+    }
+}
@@ -0,0 +1,450 @@
+//! Wave-based enemy spawning.
+//!
+//! The [`WaveDirector`] is owned by the [`Game`](crate::Game) plugin. It instantiates bots from
+//! a prefab according to the waves described in `waves.ron`, turns each one into one of the
+//! [archetypes](crate::enemy) of the wave, waits for each wave to be cleared and rests before
+//! starting the next one. Waves past the end of the list are generated by escalating the last
+//! one, unless a [boss](crate::boss) comes after a given wave: waves then stop until it is
+//! defeated, which wins the run.
+
+use crate::{
+    arena::Arena,
+    bot::Bot,
+    enemy::{Archetype, ArchetypeRegistry},
+    event::{BotSpawned, Event, EventBus},
+    health::Damageable,
+    random_point_around,
+};
+use fyrox::{
+    asset::manager::ResourceManager,
+    core::{
+        algebra::{UnitQuaternion, Vector2, Vector3},
+        log::Log,
+        pool::Handle,
+        reflect::prelude::*,
+        visitor::prelude::*,
+    },
+    graph::BaseSceneGraph,
+    rand::{seq::SliceRandom, Rng},
+    resource::model::{Model, ModelResource, ModelResourceExtension},
+    scene::{node::Node, Scene},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Location of the wave definitions, relative to the working directory of the game.
+pub const WAVES_PATH: &str = "waves.ron";
+
+/// Spawn distance range around the player used when no spawn points are configured.
+const FALLBACK_SPAWN_DISTANCE: (f32, f32) = (5.0, 11.0);
+/// Spawn distance range of summoned bots around their summoner.
+const SUMMON_DISTANCE: (f32, f32) = (1.0, 2.5);
+
+fn default_health_multiplier() -> f32 {
+    1.0
+}
+
+/// A single wave of enemies.
+#[derive(Deserialize, Debug, Clone)]
+pub struct WaveDefinition {
+    /// Number of bots spawned during the wave.
+    pub count: u32,
+    /// Seconds between two consecutive spawns.
+    pub interval: f32,
+    /// Seconds of rest after the wave is cleared.
+    pub rest: f32,
+    /// Multiplier applied to the max health of the archetype.
+    #[serde(default = "default_health_multiplier")]
+    pub health_multiplier: f32,
+    /// Archetypes spawned during the wave, with their relative weights. Bots keep the stats of
+    /// the prefab if empty.
+    #[serde(default)]
+    pub enemies: Vec<(String, u32)>,
+}
+
+/// How waves past the end of the list get harder.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Escalation {
+    /// Bots added per escalated wave.
+    pub extra_count: u32,
+    /// Spawn interval is multiplied by this factor per escalated wave.
+    pub interval_factor: f32,
+    pub min_interval: f32,
+    /// Health multiplier grows by this amount per escalated wave.
+    pub health_growth: f32,
+}
+
+impl Default for Escalation {
+    fn default() -> Self {
+        Self {
+            extra_count: 2,
+            interval_factor: 0.9,
+            min_interval: 0.5,
+            health_growth: 0.1,
+        }
+    }
+}
+
+/// Boss fight ending the run.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BossWave {
+    /// The boss comes after the rest following this wave, counted from 1.
+    pub after_wave: u32,
+    /// Archetype of the boss, with its phases.
+    pub archetype: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct WaveConfig {
+    /// Path of the bot prefab.
+    pub prefab: String,
+    /// Seconds before the first wave starts.
+    pub first_rest: f32,
+    /// Spawn points in world coordinates. Bots spawn around the player if empty.
+    #[serde(default)]
+    pub spawn_points: Vec<(f32, f32)>,
+    pub waves: Vec<WaveDefinition>,
+    #[serde(default)]
+    pub escalation: Escalation,
+    #[serde(default)]
+    pub boss: Option<BossWave>,
+}
+
+impl WaveConfig {
+    /// Returns the definition of the wave with the given zero-based index, escalating the last
+    /// listed wave if `index` is past the end of the list.
+    pub fn wave(&self, index: u32) -> Option<WaveDefinition> {
+        let last = self.waves.len().checked_sub(1)?;
+        let Some(extra) = (index as usize).checked_sub(last).filter(|e| *e > 0) else {
+            return self.waves.get(index as usize).cloned();
+        };
+
+        let base = &self.waves[last];
+        let e = &self.escalation;
+        Some(WaveDefinition {
+            count: base.count + e.extra_count * extra as u32,
+            interval: (base.interval * e.interval_factor.powi(extra as i32)).max(e.min_interval),
+            rest: base.rest,
+            health_multiplier: base.health_multiplier + e.health_growth * extra as f32,
+            enemies: base.enemies.clone(),
+        })
+    }
+}
+
+#[derive(Visit, Reflect, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WavePhase {
+    /// Waiting for the next wave to start.
+    #[default]
+    Resting,
+    /// Bots of the current wave are being spawned.
+    Spawning,
+    /// Every bot of the current wave is out, waiting for them to be defeated.
+    Fighting,
+    /// The boss is out; no more waves start.
+    Boss,
+    /// The boss was defeated.
+    Won,
+}
+
+/// Notifications about wave progress, meant to be shown by the UI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WaveEvent {
+    Started { wave: u32, enemies: u32 },
+    Cleared { wave: u32 },
+    BossStarted { boss: Handle<Node> },
+    BossDefeated,
+}
+
+impl Event for WaveEvent {}
+
+#[derive(Visit, Reflect, Debug, Default)]
+pub struct WaveDirector {
+    /// Number of the current wave, starting at 1. Zero before the first wave.
+    pub wave: u32,
+    pub phase: WavePhase,
+    /// Time left until the next wave (resting) or the next spawn (spawning).
+    pub timer: f32,
+    to_spawn: u32,
+    spawned: Vec<Handle<Node>>,
+    /// The boss, once spawned.
+    pub boss: Handle<Node>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    prefab: Option<ModelResource>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    archetypes: Arc<ArchetypeRegistry>,
+    /// Area bots spawn in, handed over by the game once it read the scene.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub arena: Arena,
+    /// Spawn points marked in the scene, used instead of those of the wave config.
+    #[visit(skip)]
+    #[reflect(hidden)]
+    pub spawn_points: Vec<Vector2<f32>>,
+}
+
+impl WaveDirector {
+    /// Starts over from the first wave.
+    pub fn reset(
+        &mut self,
+        config: &WaveConfig,
+        archetypes: &Arc<ArchetypeRegistry>,
+        resource_manager: &ResourceManager,
+    ) {
+        *self = Self {
+            timer: config.first_rest,
+            ..Default::default()
+        };
+        self.load_assets(config, archetypes, resource_manager);
+    }
+
+    /// Requests the bot prefab of `config` and keeps the archetypes the waves spawn, neither of
+    /// which is saved with the director.
+    pub fn load_assets(
+        &mut self,
+        config: &WaveConfig,
+        archetypes: &Arc<ArchetypeRegistry>,
+        resource_manager: &ResourceManager,
+    ) {
+        self.prefab =
+            (!config.prefab.is_empty()).then(|| resource_manager.request::<Model>(&config.prefab));
+        self.archetypes = archetypes.clone();
+    }
+
+    /// Number of bots of the current wave still alive.
+    pub fn alive(&self) -> usize {
+        self.spawned.len()
+    }
+
+    /// Advances the current wave, publishing [`WaveEvent`]s and a [`BotSpawned`] per bot.
+    pub fn update(
+        &mut self,
+        config: &WaveConfig,
+        scene: &mut Scene,
+        player: Handle<Node>,
+        dt: f32,
+        rng: &mut impl Rng,
+        events: &mut EventBus,
+    ) {
+        // Bots remove themselves from the scene once defeated.
+        self.spawned.retain(|h| scene.graph.is_valid_handle(*h));
+
+        let Some(definition) = config.wave(self.wave.saturating_sub(1)) else {
+            return;
+        };
+
+        match self.phase {
+            WavePhase::Resting => {
+                self.timer -= dt;
+                let boss = config.boss.as_ref().filter(|b| b.after_wave == self.wave);
+                if let (Some(boss), true) = (boss, self.timer <= 0.0) {
+                    self.boss = self.spawn_boss(config, boss, scene, player, rng);
+                    if self.boss.is_some() {
+                        self.spawned.push(self.boss);
+                        events.publish(BotSpawned { bot: self.boss });
+                        events.publish(WaveEvent::BossStarted { boss: self.boss });
+                        self.phase = WavePhase::Boss;
+                    }
+                } else if self.timer <= 0.0 {
+                    self.wave += 1;
+                    let definition = config.wave(self.wave - 1).unwrap_or(definition);
+                    self.to_spawn = definition.count;
+                    self.timer = 0.0;
+                    self.phase = WavePhase::Spawning;
+                    events.publish(WaveEvent::Started {
+                        wave: self.wave,
+                        enemies: definition.count,
+                    });
+                }
+            }
+            WavePhase::Spawning => {
+                self.timer -= dt;
+                if self.timer <= 0.0 && self.to_spawn > 0 {
+                    let bot = self.spawn_bot(config, &definition, scene, player, rng);
+                    if bot.is_some() {
+                        self.spawned.push(bot);
+                        events.publish(BotSpawned { bot });
+                        self.to_spawn -= 1;
+                        self.timer = definition.interval;
+                    }
+                }
+                if self.to_spawn == 0 {
+                    self.phase = WavePhase::Fighting;
+                }
+            }
+            WavePhase::Fighting => {
+                if self.spawned.is_empty() {
+                    events.publish(WaveEvent::Cleared { wave: self.wave });
+                    self.timer = definition.rest;
+                    self.phase = WavePhase::Resting;
+                }
+            }
+            WavePhase::Boss => {
+                if !scene.graph.is_valid_handle(self.boss) {
+                    events.publish(WaveEvent::BossDefeated);
+                    self.phase = WavePhase::Won;
+                }
+            }
+            WavePhase::Won => {}
+        }
+    }
+
+    /// Spawns `count` bots of the archetype `name` around `center`, e.g. for a boss summoning
+    /// help. They count as bots of the current wave. Returns the bots spawned, none while the
+    /// prefab is still loading.
+    pub fn summon(
+        &mut self,
+        name: &str,
+        count: u32,
+        center: Vector2<f32>,
+        scene: &mut Scene,
+        rng: &mut impl Rng,
+        events: &mut EventBus,
+    ) -> Vec<Handle<Node>> {
+        let archetypes = self.archetypes.clone();
+        let Some(archetype) = archetypes.get(name) else {
+            Log::err(format!("Unknown enemy archetype {name}"));
+            return Vec::new();
+        };
+        let mut summoned = Vec::new();
+        for _ in 0..count {
+            let (min_r, max_r) = SUMMON_DISTANCE;
+            let position = random_point_around(center, min_r, max_r, &self.arena, rng);
+            let bot = self.instantiate(Some(archetype), 1.0, position, scene);
+            if bot.is_none() {
+                break;
+            }
+            self.spawned.push(bot);
+            events.publish(BotSpawned { bot });
+            summoned.push(bot);
+        }
+        summoned
+    }
+
+    /// Instantiates the boss at one of the spawn points. Returns [`Handle::NONE`] while the
+    /// prefab is still loading.
+    fn spawn_boss(
+        &mut self,
+        config: &WaveConfig,
+        boss: &BossWave,
+        scene: &mut Scene,
+        player: Handle<Node>,
+        rng: &mut impl Rng,
+    ) -> Handle<Node> {
+        if !self.prefab.as_ref().is_some_and(|p| p.is_ok()) {
+            return self.instantiate(None, 1.0, Vector2::zeros(), scene);
+        }
+        let position = spawn_position(config, &self.spawn_points, scene, player, &self.arena, rng);
+        let archetypes = self.archetypes.clone();
+        let archetype = archetypes.get(&boss.archetype);
+        if archetype.is_none() {
+            Log::err(format!("Unknown boss archetype {}", boss.archetype));
+        }
+        self.instantiate(archetype, 1.0, position, scene)
+    }
+
+    /// Instantiates a bot at one of the spawn points. Returns [`Handle::NONE`] while the prefab
+    /// is still loading.
+    fn spawn_bot(
+        &mut self,
+        config: &WaveConfig,
+        definition: &WaveDefinition,
+        scene: &mut Scene,
+        player: Handle<Node>,
+        rng: &mut impl Rng,
+    ) -> Handle<Node> {
+        if !self.prefab.as_ref().is_some_and(|p| p.is_ok()) {
+            // Don't consume random numbers until the bot can be spawned.
+            return self.instantiate(None, 1.0, Vector2::zeros(), scene);
+        }
+
+        let position = spawn_position(config, &self.spawn_points, scene, player, &self.arena, rng);
+
+        let archetypes = self.archetypes.clone();
+        let archetype = definition
+            .enemies
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .ok()
+            .and_then(|(name, _)| {
+                let archetype = archetypes.get(name);
+                if archetype.is_none() {
+                    Log::err(format!("Unknown enemy archetype {name}"));
+                }
+                archetype
+            });
+        self.instantiate(archetype, definition.health_multiplier, position, scene)
+    }
+
+    /// Instantiates the prefab at `position` as a bot of `archetype`, with its max health
+    /// multiplied by `health_multiplier`. Returns [`Handle::NONE`] while the prefab is still
+    /// loading.
+    fn instantiate(
+        &mut self,
+        archetype: Option<&Archetype>,
+        health_multiplier: f32,
+        position: Vector2<f32>,
+        scene: &mut Scene,
+    ) -> Handle<Node> {
+        let Some(prefab) = self.prefab.as_ref().filter(|p| p.is_ok()) else {
+            if let Some(failed) = self.prefab.take_if(|p| p.is_failed_to_load()) {
+                Log::err(format!("Unable to load bot prefab {}", failed.kind()));
+            }
+            return Handle::NONE;
+        };
+        let bot = prefab.instantiate_at(
+            scene,
+            Vector3::new(position.x, position.y, 0.0),
+            UnitQuaternion::identity(),
+        );
+        let node = &mut scene.graph[bot];
+        if let Some(archetype) = archetype {
+            node.set_name(&archetype.name);
+        }
+        if let Some(script) = node.try_get_script_mut::<Bot>() {
+            script.set_respawn(false);
+            if let Some(archetype) = archetype {
+                script.set_archetype(archetype.clone());
+            }
+            let max = script.health().max() * health_multiplier;
+            script.health_mut().set_max(max);
+        }
+        node.set_visibility(true);
+
+        println!(
+            "▶ Wave {} {} spawned at ({:.2}, {:.2})",
+            self.wave,
+            node.name(),
+            position.x,
+            position.y
+        );
+        bot
+    }
+}
+
+/// One of the spawn points of the scene, else of `config`, or a point around the player if
+/// there are none.
+fn spawn_position(
+    config: &WaveConfig,
+    scene_points: &[Vector2<f32>],
+    scene: &Scene,
+    player: Handle<Node>,
+    arena: &Arena,
+    rng: &mut impl Rng,
+) -> Vector2<f32> {
+    if let Some(&position) = scene_points.choose(rng) {
+        return position;
+    }
+    match config.spawn_points.choose(rng) {
+        Some(&(x, y)) => Vector2::new(x, y),
+        None => {
+            let player_pos = scene
+                .graph
+                .try_get(player)
+                .map(|n| n.global_position().xy())
+                .unwrap_or_default();
+            let (min_r, max_r) = FALLBACK_SPAWN_DISTANCE;
+            random_point_around(player_pos, min_r, max_r, arena, rng)
+        }
+    }
+}